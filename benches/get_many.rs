@@ -0,0 +1,61 @@
+// Compares `get_many` against the same lookups done one `get` at a time, on a
+// table far larger than the CPU caches so that most probes miss. Run with
+// `cargo bench --bench get_many`.
+
+extern crate hashmap_rs;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use hashmap_rs::HashMap;
+
+const N_ENTRIES: u64 = 1 << 22;
+const BATCH: usize = 8;
+const N_BATCHES: usize = 1 << 20;
+
+fn next_key(state: &mut u64) -> u64 {
+    // xorshift64, so the keys jump around the table without a `rand` dependency.
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state % N_ENTRIES
+}
+
+fn time(f: impl FnOnce() -> u64) -> Duration {
+    let start = Instant::now();
+    black_box(f());
+    start.elapsed()
+}
+
+fn main() {
+    let map: HashMap<u64, u64> = (0..N_ENTRIES).map(|i| (i, i)).collect();
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let batches: Vec<[u64; BATCH]> = (0..N_BATCHES)
+        .map(|_| [(); BATCH].map(|_| next_key(&mut state)))
+        .collect();
+
+    let one_by_one = time(|| {
+        let mut sum = 0;
+        for batch in &batches {
+            for key in batch {
+                sum += map.get(key).copied().unwrap_or(0);
+            }
+        }
+        sum
+    });
+    let batched = time(|| {
+        let mut sum = 0;
+        for batch in &batches {
+            sum += map
+                .get_many(batch.each_ref())
+                .into_iter()
+                .flatten()
+                .sum::<u64>();
+        }
+        sum
+    });
+
+    let per_lookup = |elapsed: Duration| elapsed.as_nanos() as f64 / (N_BATCHES * BATCH) as f64;
+    println!("get:      {:6.1} ns/lookup", per_lookup(one_by_one));
+    println!("get_many: {:6.1} ns/lookup", per_lookup(batched));
+}
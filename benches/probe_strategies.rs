@@ -0,0 +1,38 @@
+// Runs the same churn of inserts, hits, misses and removals through
+// `ProbingHashMap` with each probe strategy. Run with
+// `cargo bench --bench probe_strategies`.
+
+extern crate hashmap_rs;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use hashmap_rs::probing::{DoubleHashing, Linear, ProbeStrategy, ProbingHashMap, Quadratic};
+
+const N_KEYS: u64 = 1 << 20;
+
+fn churn<P: ProbeStrategy>() -> Duration {
+    let start = Instant::now();
+    let mut map: ProbingHashMap<u64, u64, P> = ProbingHashMap::new();
+    for key in 0..N_KEYS {
+        map.insert(key, key);
+    }
+    let mut found = 0;
+    for key in 0..2 * N_KEYS {
+        found += map.get(&key).is_some() as u64;
+    }
+    for key in (0..N_KEYS).step_by(2) {
+        map.remove(&key);
+    }
+    for key in N_KEYS..N_KEYS + N_KEYS / 2 {
+        map.insert(key, key);
+    }
+    black_box((found, map.len()));
+    start.elapsed()
+}
+
+fn main() {
+    println!("linear:         {:?}", churn::<Linear>());
+    println!("quadratic:      {:?}", churn::<Quadratic>());
+    println!("double hashing: {:?}", churn::<DoubleHashing>());
+}
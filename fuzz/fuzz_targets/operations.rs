@@ -0,0 +1,82 @@
+#![no_main]
+
+use std::collections::HashMap as StdHashMap;
+
+use hashmap_rs::backend::Backend;
+use hashmap_rs::cuckoo::CuckooHashMap;
+use hashmap_rs::hopscotch::HopscotchHashMap;
+use hashmap_rs::probing::{DoubleHashing, Linear, ProbingHashMap, Quadratic};
+use hashmap_rs::two_choice::TwoChoiceHashMap;
+use hashmap_rs::HashMap;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Insert(u16, u8),
+    Get(u16),
+    Remove(u16),
+    // Inserts a run of consecutive keys so that a short input still crosses
+    // several resize thresholds.
+    Fill(u16, u8),
+}
+
+fn decode(data: &[u8]) -> Vec<Op> {
+    data.chunks_exact(4)
+        .map(|chunk| {
+            // Keys mostly come from a small range so operations collide.
+            let key = u16::from_le_bytes([chunk[1], chunk[2] & 0x03]);
+            match chunk[0] % 4 {
+                0 => Op::Insert(key, chunk[3]),
+                1 => Op::Get(key),
+                2 => Op::Remove(key),
+                _ => Op::Fill(key, chunk[3]),
+            }
+        })
+        .collect()
+}
+
+fn check<B: Backend<u16, u8>>(ops: &[Op]) {
+    let mut map = B::default();
+    let mut model = StdHashMap::new();
+
+    for &op in ops {
+        match op {
+            Op::Insert(key, value) => {
+                assert_eq!(map.insert(key, value), model.insert(key, value));
+            }
+            Op::Get(key) => {
+                assert_eq!(map.get(&key), model.get(&key));
+                assert_eq!(map.contains_key(&key), model.contains_key(&key));
+            }
+            Op::Remove(key) => {
+                assert_eq!(map.remove(&key), model.remove(&key));
+            }
+            Op::Fill(start, count) => {
+                for key in (start..).take(count as usize) {
+                    assert_eq!(map.insert(key, count), model.insert(key, count));
+                }
+            }
+        }
+        assert_eq!(map.len(), model.len());
+        assert_eq!(map.is_empty(), model.is_empty());
+        map.check_invariants();
+    }
+
+    let mut seen = 0;
+    for (key, value) in map.iter() {
+        assert_eq!(model.get(key), Some(value));
+        seen += 1;
+    }
+    assert_eq!(seen, model.len());
+}
+
+fuzz_target!(|data: &[u8]| {
+    let ops = decode(data);
+    check::<HashMap<u16, u8>>(&ops);
+    check::<TwoChoiceHashMap<u16, u8>>(&ops);
+    check::<HopscotchHashMap<u16, u8>>(&ops);
+    check::<CuckooHashMap<u16, u8>>(&ops);
+    check::<ProbingHashMap<u16, u8, Linear>>(&ops);
+    check::<ProbingHashMap<u16, u8, Quadratic>>(&ops);
+    check::<ProbingHashMap<u16, u8, DoubleHashing>>(&ops);
+});
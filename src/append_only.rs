@@ -0,0 +1,160 @@
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::cell::{Cell, UnsafeCell};
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use crate::HashMap;
+
+// Values are heap-allocated once and only ever freed when the whole map is
+// dropped, so a `&V` handed out through `&self` stays valid while the table
+// underneath keeps growing. The table holds raw pointers rather than boxes so
+// that moving entries during a resize does not invalidate those references.
+pub struct AppendOnlyMap<K, V> {
+    map: UnsafeCell<HashMap<K, NonNull<V>>>,
+    in_use: Cell<bool>,
+    _owns: PhantomData<V>,
+}
+
+// Clears `in_use` when dropped, so a panic in a key's `Hash` or `Eq` does not
+// leave the map locked for good.
+struct Release<'a>(&'a Cell<bool>);
+
+impl Drop for Release<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+// SAFETY: the map owns its keys and values outright; the raw pointers are
+// never shared outside of references tied to a borrow of the map.
+unsafe impl<K: Send, V: Send> Send for AppendOnlyMap<K, V> {}
+
+impl<K, V> AppendOnlyMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        AppendOnlyMap {
+            map: UnsafeCell::new(HashMap::new()),
+            in_use: Cell::new(false),
+            _owns: PhantomData,
+        }
+    }
+
+    fn with_map<R>(&self, f: impl FnOnce(&mut HashMap<K, NonNull<V>>) -> R) -> R {
+        // A key's `Hash` or `Eq` impl could reach back into this map; refuse
+        // rather than hand out a second reference to the table.
+        assert!(
+            !self.in_use.replace(true),
+            "AppendOnlyMap accessed while already in use"
+        );
+        let _release = Release(&self.in_use);
+        // SAFETY: `in_use` makes this the only live reference to the table,
+        // and the map is not `Sync`, so no other thread can reach it.
+        f(unsafe { &mut *self.map.get() })
+    }
+
+    // Keeps the existing value if `key` is already present, since replacing
+    // it would free memory that earlier callers may still reference.
+    pub fn insert(&self, key: K, value: V) -> &V {
+        let ptr = self.with_map(|map| {
+            if let Some(&ptr) = map.get(&key) {
+                return ptr;
+            }
+            let ptr = NonNull::from(Box::leak(Box::new(value)));
+            map.insert(key, ptr);
+            ptr
+        });
+        // SAFETY: the allocation lives until the map is dropped, which the
+        // borrow of `self` prevents for the lifetime of the reference.
+        unsafe { ptr.as_ref() }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let ptr = self.with_map(|map| map.get(key).copied())?;
+        // SAFETY: as in `insert`.
+        Some(unsafe { ptr.as_ref() })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.with_map(|map| map.contains_key(key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.with_map(|map| map.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> Default for AppendOnlyMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for AppendOnlyMap<K, V> {
+    fn drop(&mut self) {
+        for (_, &ptr) in &*self.map.get_mut() {
+            // SAFETY: every pointer came from `Box::leak` and is freed once.
+            drop(unsafe { Box::from_raw(ptr.as_ptr()) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::AssertUnwindSafe;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    #[test]
+    fn references_survive_later_inserts() {
+        let map = AppendOnlyMap::new();
+        let first = map.insert(0, "zero".to_string());
+        let refs: Vec<&String> = (1..1000).map(|i| map.insert(i, i.to_string())).collect();
+
+        assert_eq!(first, "zero");
+        assert_eq!(refs[41], "42");
+        assert_eq!(map.insert(0, "replaced".to_string()), "zero");
+        assert_eq!(map.get(&999).map(String::as_str), Some("999"));
+        assert!(!map.contains_key(&1000));
+        assert_eq!(map.len(), 1000);
+    }
+
+    #[test]
+    fn stays_usable_after_a_panicking_key() {
+        #[derive(PartialEq, Eq)]
+        struct Fragile(u32);
+
+        impl Hash for Fragile {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                assert!(self.0 != 13, "unlucky key");
+                self.0.hash(state);
+            }
+        }
+
+        let map = AppendOnlyMap::new();
+        map.insert(Fragile(1), 1);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| map.insert(Fragile(13), 13)));
+        assert!(result.is_err());
+        assert_eq!(map.get(&Fragile(1)), Some(&1));
+        assert_eq!(map.len(), 1);
+    }
+}
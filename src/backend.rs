@@ -0,0 +1,159 @@
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+use crate::cuckoo::CuckooHashMap;
+use crate::hopscotch::HopscotchHashMap;
+use crate::probing::{ProbeStrategy, ProbingHashMap};
+use crate::two_choice::TwoChoiceHashMap;
+use crate::HashMap;
+
+// The operations every table layout in this crate supports. Code that takes a
+// `B: Backend<K, V>` type parameter can switch between `HashMap` (a flat Robin
+// Hood table with short, even probe runs), `TwoChoiceHashMap` (chained, with
+// short chains even under poor hashing), `HopscotchHashMap` (flat and dense),
+// `CuckooHashMap` (two probes per lookup, worst case) and `ProbingHashMap`
+// (flat, with a pluggable probe sequence) without other changes.
+pub trait Backend<K, V>: Default {
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(key).is_some()
+    }
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a K, &'a V)>
+    where
+        K: 'a,
+        V: 'a;
+
+    // Panics if the table's internal structure is inconsistent. For fuzzing
+    // and tests; the crate's backends override it, and other implementers
+    // can leave it empty.
+    #[doc(hidden)]
+    fn check_invariants(&self) {}
+}
+
+pub type DefaultBackend<K, V> = HashMap<K, V>;
+
+macro_rules! impl_backend {
+    ($map:ident $(<$p:ident: $bound:path>)?, |$this:ident| $iter:expr) => {
+        impl<K, V $(, $p: $bound)?> Backend<K, V> for $map<K, V $(, $p)?>
+        where
+            K: Hash + Eq,
+        {
+            fn insert(&mut self, key: K, value: V) -> Option<V> {
+                $map::insert(self, key, value)
+            }
+
+            fn get<Q>(&self, key: &Q) -> Option<&V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                $map::get(self, key)
+            }
+
+            fn remove<Q>(&mut self, key: &Q) -> Option<V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                $map::remove(self, key)
+            }
+
+            fn contains_key<Q>(&self, key: &Q) -> bool
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                $map::contains_key(self, key)
+            }
+
+            fn len(&self) -> usize {
+                $map::len(self)
+            }
+
+            fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a K, &'a V)>
+            where
+                K: 'a,
+                V: 'a,
+            {
+                let $this = self;
+                $iter
+            }
+
+            fn check_invariants(&self) {
+                $map::check_invariants(self)
+            }
+        }
+    };
+}
+
+impl_backend!(HashMap, |map| HashMap::iter(map));
+impl_backend!(CuckooHashMap, |map| CuckooHashMap::iter(map));
+impl_backend!(HopscotchHashMap, |map| HopscotchHashMap::iter(map));
+impl_backend!(TwoChoiceHashMap, |map| TwoChoiceHashMap::iter(map));
+impl_backend!(ProbingHashMap<P: ProbeStrategy>, |map| ProbingHashMap::iter(map));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probing::{DoubleHashing, Linear, Quadratic};
+    use std::string::{String, ToString};
+
+    fn exercise<B: Backend<String, usize>>() {
+        let mut map = B::default();
+        assert!(map.is_empty());
+        assert_eq!(map.get("foo"), None);
+        assert_eq!(map.remove("foo"), None);
+
+        for i in 0..500 {
+            assert_eq!(map.insert(i.to_string(), i), None);
+        }
+        assert_eq!(map.insert("42".to_string(), 4200), Some(42));
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get("42"), Some(&4200));
+        assert_eq!(map.iter().count(), 500);
+
+        map.check_invariants();
+
+        for i in (0..500).step_by(2) {
+            assert!(map.remove(i.to_string().as_str()).is_some());
+        }
+        map.check_invariants();
+        assert_eq!(map.len(), 250);
+        for i in 0..500 {
+            assert_eq!(map.contains_key(i.to_string().as_str()), i % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn backends_share_behavior() {
+        exercise::<DefaultBackend<String, usize>>();
+        exercise::<CuckooHashMap<String, usize>>();
+        exercise::<HopscotchHashMap<String, usize>>();
+        exercise::<TwoChoiceHashMap<String, usize>>();
+        exercise::<ProbingHashMap<String, usize, Linear>>();
+        exercise::<ProbingHashMap<String, usize, Quadratic>>();
+        exercise::<ProbingHashMap<String, usize, DoubleHashing>>();
+    }
+}
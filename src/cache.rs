@@ -0,0 +1,355 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::HashMap;
+
+const NIL: usize = usize::MAX;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    // `None` when the TTL reaches past what an `Instant` can represent.
+    expires_at: Option<Instant>,
+    recency: Links,
+    writes: Links,
+}
+
+impl<K, V> Node<K, V> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Links {
+    prev: usize,
+    next: usize,
+}
+
+const UNLINKED: Links = Links {
+    prev: NIL,
+    next: NIL,
+};
+
+// The ends of one intrusive list, newest at `head`.
+#[derive(Clone, Copy)]
+struct List {
+    head: usize,
+    tail: usize,
+}
+
+const EMPTY: List = List {
+    head: NIL,
+    tail: NIL,
+};
+
+#[derive(Clone, Copy)]
+enum Order {
+    Recency,
+    Writes,
+}
+
+// Entries sit in a slab threaded by two intrusive lists, and the map
+// resolves keys to slab slots. One list orders entries by use, for LRU
+// eviction. The other orders them by last write: every write restarts the
+// same TTL, so entries expire in that order and the oldest write is the only
+// one worth checking for room. Expired entries are never returned: they are
+// dropped when looked up, when `reap` runs, or when a full cache needs room.
+pub struct TtlLruCache<K, V> {
+    index: HashMap<K, usize>,
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
+    recency: List,
+    writes: List,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl<K, V> TtlLruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        assert!(capacity > 0, "a cache needs room for at least one entry");
+        TtlLruCache {
+            index: HashMap::new(),
+            nodes: Vec::new(),
+            free: Vec::new(),
+            recency: EMPTY,
+            writes: EMPTY,
+            capacity,
+            ttl,
+        }
+    }
+
+    fn node(&self, idx: usize) -> &Node<K, V> {
+        self.nodes[idx].as_ref().expect("linked slot is occupied")
+    }
+
+    fn node_mut(&mut self, idx: usize) -> &mut Node<K, V> {
+        self.nodes[idx].as_mut().expect("linked slot is occupied")
+    }
+
+    fn list_mut(&mut self, order: Order) -> &mut List {
+        match order {
+            Order::Recency => &mut self.recency,
+            Order::Writes => &mut self.writes,
+        }
+    }
+
+    fn links_mut(&mut self, idx: usize, order: Order) -> &mut Links {
+        let node = self.node_mut(idx);
+        match order {
+            Order::Recency => &mut node.recency,
+            Order::Writes => &mut node.writes,
+        }
+    }
+
+    fn unlink(&mut self, idx: usize, order: Order) {
+        let Links { prev, next } = *self.links_mut(idx, order);
+        match prev {
+            NIL => self.list_mut(order).head = next,
+            prev => self.links_mut(prev, order).next = next,
+        }
+        match next {
+            NIL => self.list_mut(order).tail = prev,
+            next => self.links_mut(next, order).prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, idx: usize, order: Order) {
+        let head = self.list_mut(order).head;
+        *self.links_mut(idx, order) = Links {
+            prev: NIL,
+            next: head,
+        };
+        match head {
+            NIL => self.list_mut(order).tail = idx,
+            head => self.links_mut(head, order).prev = idx,
+        }
+        self.list_mut(order).head = idx;
+    }
+
+    fn move_to_front(&mut self, idx: usize, order: Order) {
+        self.unlink(idx, order);
+        self.push_front(idx, order);
+    }
+
+    fn take_node(&mut self, idx: usize) -> Node<K, V> {
+        self.unlink(idx, Order::Recency);
+        self.unlink(idx, Order::Writes);
+        let node = self.nodes[idx].take().expect("linked slot is occupied");
+        self.index.remove(&node.key);
+        self.free.push(idx);
+        node
+    }
+
+    fn insert_at(&mut self, key: K, value: V, now: Instant) -> Option<V> {
+        let expires_at = now.checked_add(self.ttl);
+        if let Some(&idx) = self.index.get(&key) {
+            let node = self.node_mut(idx);
+            let expired = node.is_expired(now);
+            node.expires_at = expires_at;
+            let old_value = mem::replace(&mut node.value, value);
+            self.move_to_front(idx, Order::Recency);
+            self.move_to_front(idx, Order::Writes);
+            return (!expired).then_some(old_value);
+        }
+
+        // An expired entry makes room before any live one is evicted. If
+        // any has expired, the oldest write has.
+        if self.index.len() == self.capacity {
+            let oldest = self.writes.tail;
+            if self.node(oldest).is_expired(now) {
+                self.take_node(oldest);
+            } else {
+                self.take_node(self.recency.tail);
+            }
+        }
+
+        let node = Node {
+            key: key.clone(),
+            value,
+            expires_at,
+            recency: UNLINKED,
+            writes: UNLINKED,
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = Some(node);
+                idx
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.index.insert(key, idx);
+        self.push_front(idx, Order::Recency);
+        self.push_front(idx, Order::Writes);
+        None
+    }
+
+    fn remove_at<Q>(&mut self, key: &Q, now: Instant) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = *self.index.get(key)?;
+        let node = self.take_node(idx);
+        (!node.is_expired(now)).then_some(node.value)
+    }
+
+    fn get_at<Q>(&mut self, key: &Q, now: Instant) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = *self.index.get(key)?;
+        if self.node(idx).is_expired(now) {
+            self.take_node(idx);
+            return None;
+        }
+        self.move_to_front(idx, Order::Recency);
+        Some(&self.node(idx).value)
+    }
+
+    // Only visits the expired entries and the first live one after them.
+    fn reap_at(&mut self, now: Instant) -> usize {
+        let mut reaped = 0;
+        while self.writes.tail != NIL && self.node(self.writes.tail).is_expired(now) {
+            self.take_node(self.writes.tail);
+            reaped += 1;
+        }
+        reaped
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_at(key, value, Instant::now())
+    }
+
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_at(key, Instant::now())
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.index
+            .get(key)
+            .is_some_and(|&idx| !self.node(idx).is_expired(Instant::now()))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.remove_at(key, Instant::now())
+    }
+
+    // Drops every expired entry and returns how many were dropped.
+    pub fn reap(&mut self) -> usize {
+        self.reap_at(Instant::now())
+    }
+
+    // Includes expired entries that have not been reaped yet.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_both_ttl_and_capacity() {
+        let start = Instant::now();
+        let later = |secs| start + Duration::from_secs(secs);
+        let mut cache = TtlLruCache::new(2, Duration::from_secs(10));
+
+        cache.insert_at("a", 1, start);
+        cache.insert_at("b", 2, later(1));
+        assert_eq!(cache.get_at("a", later(2)), Some(&1));
+        cache.insert_at("c", 3, later(3));
+        assert_eq!(cache.get_at("b", later(3)), None);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.get_at("a", later(10)), None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.insert_at("c", 30, later(12)), Some(3));
+        assert_eq!(cache.insert_at("d", 4, later(12)), None);
+
+        assert_eq!(cache.reap_at(later(21)), 0);
+        assert_eq!(cache.reap_at(later(22)), 2);
+        assert!(cache.is_empty());
+        assert_eq!(cache.insert_at("e", 5, later(30)), None);
+        assert_eq!(cache.remove("e"), Some(5));
+    }
+
+    #[test]
+    fn remove_drops_expired_entries_without_returning_them() {
+        let start = Instant::now();
+        let mut cache = TtlLruCache::new(2, Duration::from_secs(10));
+
+        cache.insert_at("e", 5, start);
+        assert_eq!(cache.remove_at("e", start + Duration::from_secs(10)), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn full_cache_reclaims_expired_entries_before_evicting() {
+        let start = Instant::now();
+        let later = |secs| start + Duration::from_secs(secs);
+        let mut cache = TtlLruCache::new(2, Duration::from_secs(10));
+
+        cache.insert_at("a", 1, start);
+        cache.insert_at("b", 2, later(5));
+        assert_eq!(cache.get_at("a", later(6)), Some(&1));
+        cache.insert_at("c", 3, later(11));
+        assert_eq!(cache.get_at("b", later(11)), Some(&2));
+        assert_eq!(cache.get_at("c", later(11)), Some(&3));
+    }
+
+    #[test]
+    fn rewriting_an_entry_restarts_its_ttl() {
+        let start = Instant::now();
+        let later = |secs| start + Duration::from_secs(secs);
+        let mut cache = TtlLruCache::new(2, Duration::from_secs(10));
+
+        cache.insert_at("a", 1, start);
+        cache.insert_at("b", 2, later(5));
+        assert_eq!(cache.insert_at("a", 10, later(6)), Some(1));
+        assert_eq!(cache.get_at("b", later(7)), Some(&2));
+        cache.insert_at("c", 3, later(15));
+        assert_eq!(cache.get_at("a", later(15)), Some(&10));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.reap_at(later(16)), 1);
+        assert_eq!(cache.get_at("c", later(16)), Some(&3));
+    }
+
+    #[test]
+    fn unbounded_ttl_never_expires() {
+        let mut cache = TtlLruCache::new(2, Duration::MAX);
+        assert_eq!(cache.insert("a", 1), None);
+        assert_eq!(cache.reap(), 0);
+        assert_eq!(cache.get("a"), Some(&1));
+    }
+}
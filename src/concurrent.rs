@@ -0,0 +1,196 @@
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use core::ops::Deref;
+use core::ptr::NonNull;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{hash_one, part_for, DefaultState, HashMap};
+
+const DEFAULT_N_SHARDS: usize = 16;
+
+// Each key lives in one of `N` independently locked maps, picked from its
+// hash by `part_for`, so threads working on different shards never contend.
+pub struct ConcurrentHashMap<K, V, S = DefaultState> {
+    shards: Box<[RwLock<HashMap<K, V, S>>]>,
+    hash_builder: S,
+}
+
+// A value borrowed from a shard, which stays read-locked until the guard is
+// dropped.
+pub struct Ref<'a, K, V, S> {
+    _shard: RwLockReadGuard<'a, HashMap<K, V, S>>,
+    value: NonNull<V>,
+}
+
+impl<K, V, S> Deref for Ref<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // SAFETY: `value` points into the shard, which cannot be changed
+        // while the read guard is held. Moving the guard does not move the
+        // map behind it.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+// A panic in a key's `Hash` or `Eq` can at worst lose entries of the shard
+// it happened in, so poisoned shards stay usable.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V> ConcurrentHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_N_SHARDS)
+    }
+
+    pub fn with_shards(n_shards: usize) -> Self {
+        Self::with_shards_and_hasher(n_shards, DefaultState::default())
+    }
+}
+
+impl<K, V, S> ConcurrentHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    pub fn with_shards_and_hasher(n_shards: usize, hash_builder: S) -> Self {
+        assert!(n_shards > 0, "a concurrent map needs at least one shard");
+        let shards = (0..n_shards)
+            .map(|_| RwLock::new(HashMap::with_hasher(hash_builder.clone())))
+            .collect();
+        ConcurrentHashMap {
+            shards,
+            hash_builder,
+        }
+    }
+
+    fn shard<Q>(&self, key: &Q) -> &RwLock<HashMap<K, V, S>>
+    where
+        Q: ?Sized + Hash,
+    {
+        let hash = hash_one(&self.hash_builder, key);
+        &self.shards[part_for(hash, self.shards.len())]
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        write(self.shard(&key)).insert(key, value)
+    }
+
+    // Holds the key's shard read-locked until the returned guard is dropped,
+    // which blocks writers to that shard in the meantime.
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let shard = read(self.shard(key));
+        let value = NonNull::from(shard.get(key)?);
+        Some(Ref {
+            _shard: shard,
+            value,
+        })
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        read(self.shard(key)).contains_key(key)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        write(self.shard(key)).remove(key)
+    }
+
+    // Locks one shard at a time, so the total can be stale by the time it
+    // is returned if other threads keep writing.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+
+    // Write-locks the shard that holds `key`, for updates that take several
+    // steps and must not interleave with other writers to that key.
+    pub fn write_shard<Q>(&self, key: &Q) -> RwLockWriteGuard<'_, HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        write(self.shard(key))
+    }
+
+    // Read-locks each shard in turn as the iterator reaches it. Dropping a
+    // guard before asking for the next one keeps a single shard locked.
+    pub fn shards(&self) -> impl Iterator<Item = RwLockReadGuard<'_, HashMap<K, V, S>>> {
+        self.shards.iter().map(read)
+    }
+}
+
+impl<K, V> Default for ConcurrentHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::IdentityState;
+    use std::thread;
+
+    #[test]
+    fn threads_share_one_map() {
+        let map = ConcurrentHashMap::with_shards(4);
+        thread::scope(|s| {
+            for t in 0..4 {
+                let map = &map;
+                s.spawn(move || {
+                    for i in (t * 250)..((t + 1) * 250) {
+                        assert_eq!(map.insert(i, i * 2), None);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.get(&21).as_deref(), Some(&42));
+        assert!(map.get(&1000).is_none());
+        assert_eq!(map.remove(&21), Some(42));
+        assert!(!map.contains_key(&21));
+        assert_eq!(map.shards().map(|shard| shard.len()).sum::<usize>(), 999);
+        assert!(map.shards().all(|shard| !shard.is_empty()));
+
+        let mut shard = map.write_shard(&7);
+        assert_eq!(shard.remove(&7), Some(14));
+        drop(shard);
+        assert!(!map.contains_key(&7));
+    }
+
+    #[test]
+    fn weak_hashes_spread_over_every_shard() {
+        let map = ConcurrentHashMap::with_shards_and_hasher(16, IdentityState::default());
+        for i in 0..10_000u64 {
+            map.insert(i, i);
+        }
+        assert!(map.shards().all(|shard| shard.len() > 500));
+    }
+}
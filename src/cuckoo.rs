@@ -0,0 +1,218 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+
+use crate::{hash_one, DefaultState, HashValue};
+
+const MAX_DISPLACEMENTS: usize = 32;
+
+type Table<K, V> = Vec<Option<(K, V)>>;
+
+// Every key has exactly one candidate slot in each of the two tables, so a
+// lookup never probes more than two slots. Inserts evict residents into their
+// other table until a free slot turns up, and rehash with fresh seeds when
+// that chain gets too long.
+pub struct CuckooHashMap<K, V> {
+    tables: [Table<K, V>; 2],
+    seed: u64,
+    hash_builder: DefaultState,
+    items: usize,
+}
+
+impl<K, V> CuckooHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        CuckooHashMap {
+            tables: [Vec::new(), Vec::new()],
+            seed: 0,
+            hash_builder: DefaultState::default(),
+            items: 0,
+        }
+    }
+
+    fn slot_idx<Q>(&self, table: usize, key: &Q) -> usize
+    where
+        Q: ?Sized + Hash,
+    {
+        let seed = 2 * self.seed + table as u64;
+        let hash = hash_one(&self.hash_builder, &(seed, key));
+        (hash % (self.tables[table].len() as HashValue)) as usize
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<(usize, usize)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if self.tables[0].is_empty() {
+            return None;
+        }
+
+        (0..2).find_map(|table| {
+            let idx = self.slot_idx(table, key);
+            match &self.tables[table][idx] {
+                Some((ekey, _)) if ekey.borrow() == key => Some((table, idx)),
+                _ => None,
+            }
+        })
+    }
+
+    fn place(&mut self, mut entry: (K, V)) -> Result<(), (K, V)> {
+        let mut table = 0;
+        for _ in 0..MAX_DISPLACEMENTS {
+            let idx = self.slot_idx(table, &entry.0);
+            match &mut self.tables[table][idx] {
+                slot @ None => {
+                    *slot = Some(entry);
+                    return Ok(());
+                }
+                Some(resident) => mem::swap(resident, &mut entry),
+            }
+            table ^= 1;
+        }
+        Err(entry)
+    }
+
+    fn rehash(&mut self, mut table_size: usize, pending: Option<(K, V)>) {
+        let mut entries: Vec<(K, V)> = self
+            .tables
+            .iter_mut()
+            .flat_map(|table| table.drain(..))
+            .flatten()
+            .chain(pending)
+            .collect();
+
+        'retry: loop {
+            self.seed += 1;
+            self.tables = [(); 2].map(|_| (0..table_size).map(|_| None).collect());
+            while let Some(entry) = entries.pop() {
+                if let Err(entry) = self.place(entry) {
+                    entries.push(entry);
+                    entries.extend(
+                        self.tables
+                            .iter_mut()
+                            .flat_map(|table| table.drain(..))
+                            .flatten(),
+                    );
+                    table_size *= 2;
+                    continue 'retry;
+                }
+            }
+            break;
+        }
+    }
+
+    // Panics unless every entry sits in the slot its own table hashes it to
+    // and the item count matches.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let mut items = 0;
+        for (table, slots) in self.tables.iter().enumerate() {
+            for (idx, slot) in slots.iter().enumerate() {
+                let Some((key, _)) = slot else { continue };
+                items += 1;
+                assert_eq!(self.slot_idx(table, key), idx, "misplaced entry");
+            }
+        }
+        assert_eq!(items, self.items, "item count is off");
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some((table, idx)) = self.find(&key) {
+            let (_, evalue) = self.tables[table][idx].as_mut()?;
+            return Some(mem::replace(evalue, value));
+        }
+
+        // Cuckoo tables degrade quickly past half occupancy.
+        let table_size = self.tables[0].len();
+        if self.items >= table_size {
+            self.rehash((2 * table_size).max(1), None);
+        }
+
+        if let Err(entry) = self.place((key, value)) {
+            self.rehash(self.tables[0].len(), Some(entry));
+        }
+        self.items += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (table, idx) = self.find(key)?;
+        self.tables[table][idx].as_ref().map(|(_, value)| value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (table, idx) = self.find(key)?;
+        self.items -= 1;
+        self.tables[table][idx].take().map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.tables
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+}
+
+impl<K, V> Default for CuckooHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = CuckooHashMap::new();
+        assert_eq!(map.get("foo"), None);
+        for i in 0..1000 {
+            assert_eq!(map.insert(i.to_string(), i), None);
+        }
+        assert_eq!(map.insert("7".to_string(), 70), Some(7));
+        assert_eq!(map.len(), 1000);
+        for i in 0..1000 {
+            let expected = if i == 7 { 70 } else { i };
+            assert_eq!(map.get(i.to_string().as_str()), Some(&expected));
+        }
+        assert_eq!(map.iter().count(), 1000);
+        assert_eq!(map.remove("7"), Some(70));
+        assert!(!map.contains_key("7"));
+        assert_eq!(map.len(), 999);
+    }
+}
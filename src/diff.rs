@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use crate::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapDiff<K, V> {
+    pub added: Vec<(K, V)>,
+    pub removed: Vec<K>,
+    // (key, old value, new value)
+    pub changed: Vec<(K, V, V)>,
+}
+
+impl<K, V> MapDiff<K, V> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: PartialEq + Clone,
+    S: BuildHasher,
+{
+    pub fn diff(&self, other: &HashMap<K, V, S>) -> MapDiff<K, V> {
+        let mut diff = MapDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+
+        for (key, value) in self {
+            match other.get(key) {
+                Some(other_value) if other_value != value => {
+                    diff.changed
+                        .push((key.clone(), value.clone(), other_value.clone()));
+                }
+                Some(_) => {}
+                None => diff.removed.push(key.clone()),
+            }
+        }
+
+        for (key, value) in other {
+            if !self.contains_key(key) {
+                diff.added.push((key.clone(), value.clone()));
+            }
+        }
+
+        diff
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn apply(&mut self, diff: MapDiff<K, V>) {
+        for key in diff.removed {
+            self.remove(&key);
+        }
+        for (key, _, new_value) in diff.changed {
+            self.insert(key, new_value);
+        }
+        for (key, value) in diff.added {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_each_kind_of_change() {
+        let mut old = HashMap::new();
+        old.insert("kept", 1);
+        old.insert("changed", 2);
+        old.insert("removed", 3);
+
+        let mut new = HashMap::new();
+        new.insert("kept", 1);
+        new.insert("changed", 20);
+        new.insert("added", 4);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, [("added", 4)]);
+        assert_eq!(diff.removed, ["removed"]);
+        assert_eq!(diff.changed, [("changed", 2, 20)]);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn apply_replays_diff() {
+        let mut old = HashMap::new();
+        old.insert("kept", 1);
+        old.insert("changed", 2);
+        old.insert("removed", 3);
+
+        let mut new = HashMap::new();
+        new.insert("kept", 1);
+        new.insert("changed", 20);
+        new.insert("added", 4);
+
+        old.apply(old.diff(&new));
+        assert!(old.diff(&new).is_empty());
+        assert_eq!(old.len(), 3);
+    }
+}
@@ -0,0 +1,374 @@
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+
+use crate::{place_slot, take_slot, HashMap, HashValue, Slot};
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+// Both entry kinds remember where the lookup ended, so acting on the entry
+// never searches the table again.
+pub struct OccupiedEntry<'a, K, V> {
+    slots: &'a mut [Option<Slot<K, V>>],
+    items: &'a mut usize,
+    index: usize,
+}
+
+pub struct VacantEntry<'a, K, V> {
+    slots: &'a mut [Option<Slot<K, V>>],
+    items: &'a mut usize,
+    hash: HashValue,
+    key: K,
+}
+
+// Looked up by a borrowed key, so an owned `K` is only built if the entry
+// turns out to be vacant and gets filled.
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V>),
+}
+
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, V> {
+    slots: &'a mut [Option<Slot<K, V>>],
+    items: &'a mut usize,
+    hash: HashValue,
+    key: &'b Q,
+}
+
+// Returned by `try_insert` when the key is already present. Holds the
+// existing entry and the value that was not inserted.
+pub struct OccupiedError<'a, K, V> {
+    pub entry: OccupiedEntry<'a, K, V>,
+    pub value: V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Display for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get()
+        )
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for OccupiedError<'_, K, V> {}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    // Unlike `insert`, leaves an existing value alone.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+            Entry::Vacant(entry) => Ok(entry.insert(value)),
+        }
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let hash = self.hash(&key);
+        if let Some(index) = self.find_hashed(hash, &key) {
+            return Entry::Occupied(OccupiedEntry {
+                slots: &mut self.slots,
+                items: &mut self.items,
+                index,
+            });
+        }
+
+        // Grow before handing out a vacant entry, as `insert` does, so that
+        // its probe run is still the right one when it gets filled.
+        self.reserve(1);
+        Entry::Vacant(VacantEntry {
+            slots: &mut self.slots,
+            items: &mut self.items,
+            hash,
+            key,
+        })
+    }
+
+    pub fn entry_ref<'b, Q>(&mut self, key: &'b Q) -> EntryRef<'_, 'b, K, Q, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let hash = self.hash(key);
+        if let Some(index) = self.find_hashed(hash, key) {
+            return EntryRef::Occupied(OccupiedEntry {
+                slots: &mut self.slots,
+                items: &mut self.items,
+                index,
+            });
+        }
+
+        self.reserve(1);
+        EntryRef::Vacant(VacantEntryRef {
+            slots: &mut self.slots,
+            items: &mut self.items,
+            hash,
+            key,
+        })
+    }
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        self.or_insert_with_key(|_| default())
+    }
+
+    pub fn or_insert_with_key(self, default: impl FnOnce(&K) -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, 'b, K, Q, V> EntryRef<'a, 'b, K, Q, V>
+where
+    K: Borrow<Q>,
+    Q: ?Sized,
+{
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        K: From<&'b Q>,
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    fn slot(&self) -> &Slot<K, V> {
+        self.slots[self.index]
+            .as_ref()
+            .expect("occupied entry is filled")
+    }
+
+    fn slot_mut(&mut self) -> &mut Slot<K, V> {
+        self.slots[self.index]
+            .as_mut()
+            .expect("occupied entry is filled")
+    }
+
+    pub fn key(&self) -> &K {
+        &self.slot().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.slot().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.slot_mut().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        let slot = self.slots[self.index].as_mut();
+        &mut slot.expect("occupied entry is filled").value
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    // The entry only borrows the slots, so unlike `HashMap::remove` this
+    // never shrinks the table.
+    pub fn remove_entry(self) -> (K, V) {
+        *self.items -= 1;
+        take_slot(self.slots, self.index)
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.items += 1;
+        let index = place_slot(self.slots, self.hash, self.key, value);
+        let slot = self.slots[index].as_mut();
+        &mut slot.expect("entry was just placed").value
+    }
+}
+
+impl<'a, 'b, K, Q: ?Sized, V> VacantEntryRef<'a, 'b, K, Q, V> {
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        self.insert_with_key(K::from, value)
+    }
+
+    // The owned key must hash and compare equal to the borrowed one, or it
+    // ends up in a slot lookups for it never probe.
+    pub fn insert_with_key(self, make_key: impl FnOnce(&'b Q) -> K, value: V) -> &'a mut V {
+        let entry = VacantEntry {
+            slots: self.slots,
+            items: self.items,
+            hash: self.hash,
+            key: make_key(self.key),
+        };
+        entry.insert(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::{String, ToString};
+
+    #[test]
+    fn counts_words_with_a_single_lookup_each() {
+        let mut counts = HashMap::new();
+        for word in "the cat saw the other cat near the door".split(' ') {
+            counts.entry(word).and_modify(|n| *n += 1).or_insert(1);
+        }
+
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts.get("the"), Some(&3));
+        assert_eq!(counts.get("cat"), Some(&2));
+        assert_eq!(*counts.entry("dog").or_default(), 0);
+
+        match counts.entry("saw") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), ("saw", 1)),
+            Entry::Vacant(_) => unreachable!("\"saw\" was inserted above"),
+        }
+        match counts.entry("saw") {
+            Entry::Occupied(_) => unreachable!("\"saw\" was removed above"),
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), "saw"),
+        }
+        assert_eq!(counts.len(), 6);
+    }
+
+    #[test]
+    fn entry_ref_only_builds_keys_for_vacant_entries() {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut allocations = 0;
+        for word in "a b a c a b".split(' ') {
+            match counts.entry_ref(word) {
+                EntryRef::Occupied(mut entry) => *entry.get_mut() += 1,
+                EntryRef::Vacant(entry) => {
+                    allocations += 1;
+                    entry.insert_with_key(str::to_string, 1);
+                }
+            }
+        }
+        *counts.entry_ref("d").and_modify(|n| *n += 1).or_default() += 5;
+
+        assert_eq!(allocations, 3);
+        assert_eq!(counts.get("a"), Some(&3));
+        assert_eq!(counts.get("b"), Some(&2));
+        assert_eq!(counts.get("d"), Some(&5));
+        assert_eq!(counts.entry_ref("c").key(), "c");
+        assert_eq!(counts.len(), 4);
+    }
+
+    #[test]
+    fn hits_on_a_full_table_do_not_grow_it() {
+        let mut map = HashMap::with_capacity(8);
+        let capacity = map.capacity();
+        for i in 0..capacity {
+            map.insert(i, i);
+        }
+
+        *map.entry(0).or_insert(1) += 1;
+        map.entry_ref(&1).and_modify(|n| *n += 1);
+        map.insert(2, 3);
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!((map[&0], map[&1], map[&2]), (1, 2, 3));
+
+        map.entry(capacity).or_insert(0);
+        assert!(map.capacity() > capacity);
+    }
+}
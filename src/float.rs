@@ -0,0 +1,97 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+// Hashable float wrappers. Every NaN is collapsed into one canonical NaN so
+// that all NaNs land on the same entry; everything else compares by IEEE 754
+// total order, which keeps `0.0` and `-0.0` as distinct keys.
+macro_rules! float_key {
+    ($name:ident, $float:ty) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name($float);
+
+        impl $name {
+            pub fn new(value: $float) -> Self {
+                if value.is_nan() {
+                    $name(<$float>::NAN)
+                } else {
+                    $name(value)
+                }
+            }
+
+            pub fn get(self) -> $float {
+                self.0
+            }
+        }
+
+        impl From<$float> for $name {
+            fn from(value: $float) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for $float {
+            fn from(key: $name) -> Self {
+                key.0
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.to_bits() == other.0.to_bits()
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_bits().hash(state);
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+float_key!(F64Key, f64);
+float_key!(F32Key, f32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashMap;
+
+    #[test]
+    fn floats_as_keys() {
+        let mut map = HashMap::new();
+        map.insert(F64Key::from(1.5), "one and a half");
+        map.insert(F64Key::from(f64::NAN), "nan");
+        map.insert(F64Key::from(-f64::NAN), "other nan");
+        map.insert(F64Key::from(0.0), "zero");
+        map.insert(F64Key::from(-0.0), "negative zero");
+
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&F64Key::new(1.5)), Some(&"one and a half"));
+        assert_eq!(map.get(&F64Key::new(f64::NAN)), Some(&"other nan"));
+        assert_eq!(map.get(&F64Key::new(0.0)), Some(&"zero"));
+        assert_eq!(f64::from(F64Key::new(2.0)), 2.0);
+        assert!(F32Key::new(f32::NEG_INFINITY) < F32Key::new(-0.0));
+    }
+}
@@ -0,0 +1,93 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// FNV-1a. Needs neither the OS nor any state beyond one word, which makes it
+// the default hasher of maps built without `std`. It is unkeyed, so keys an
+// attacker controls can be picked to collide; use a seeded hasher for those.
+// Integers are hashed as little-endian bytes, and `usize` as a `u64`, so a
+// key hashes the same on every platform.
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher(u64);
+
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher(OFFSET_BASIS)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashMap;
+    use core::hash::BuildHasher;
+
+    #[test]
+    fn matches_reference_vectors() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = FnvHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+
+        let mut map = HashMap::with_hasher(FnvBuildHasher::default());
+        map.insert("key", 1);
+        assert_eq!(map.get("key"), Some(&1));
+        assert_eq!(
+            FnvBuildHasher::default().hash_one("key"),
+            FnvBuildHasher::default().hash_one("key")
+        );
+    }
+
+    #[test]
+    fn integers_hash_the_same_on_every_platform() {
+        let state = FnvBuildHasher::default();
+        let le_bytes = |bytes: &[u8]| {
+            let mut hasher = FnvHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(state.hash_one(0x0102_u16), le_bytes(&[2, 1]));
+        assert_eq!(state.hash_one(7_u64), le_bytes(&7_u64.to_le_bytes()));
+        assert_eq!(state.hash_one(7_usize), state.hash_one(7_u64));
+        assert_eq!(state.hash_one(-1_i32), le_bytes(&[0xff; 4]));
+    }
+}
@@ -0,0 +1,245 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+
+use crate::{hash_one, DefaultState};
+
+const NEIGHBORHOOD: usize = 32;
+const MIN_SLOTS: usize = NEIGHBORHOOD;
+
+// Every entry lives within `NEIGHBORHOOD` slots of its home slot, and each
+// home slot keeps a bitmap of which of those nearby slots hold its entries. A
+// lookup touches a single stretch of memory even when the table is 90% full.
+pub struct HopscotchHashMap<K, V> {
+    slots: Vec<Option<(K, V)>>,
+    hop_info: Vec<u32>,
+    hash_builder: DefaultState,
+    items: usize,
+}
+
+impl<K, V> HopscotchHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        HopscotchHashMap {
+            slots: Vec::new(),
+            hop_info: Vec::new(),
+            hash_builder: DefaultState::default(),
+            items: 0,
+        }
+    }
+
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn home_idx<Q>(&self, key: &Q) -> usize
+    where
+        Q: ?Sized + Hash,
+    {
+        hash_one(&self.hash_builder, key) as usize & self.mask()
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let home = self.home_idx(key);
+        let mut hops = self.hop_info[home];
+        while hops != 0 {
+            let idx = (home + hops.trailing_zeros() as usize) & self.mask();
+            if matches!(&self.slots[idx], Some((ekey, _)) if ekey.borrow() == key) {
+                return Some(idx);
+            }
+            hops &= hops - 1;
+        }
+        None
+    }
+
+    fn place(&mut self, entry: (K, V)) -> Result<(), (K, V)> {
+        let mask = self.mask();
+        let home = self.home_idx(&entry.0);
+        let Some(mut distance) =
+            (0..self.slots.len()).find(|d| self.slots[(home + d) & mask].is_none())
+        else {
+            return Err(entry);
+        };
+
+        // Hop the free slot back towards home by moving entries that may
+        // legally live in it, until it falls inside the home neighborhood.
+        while distance >= NEIGHBORHOOD {
+            let free = (home + distance) & mask;
+            let hop = (1..NEIGHBORHOOD).rev().find_map(|back| {
+                let candidate_home = free.wrapping_sub(back) & mask;
+                let movable = self.hop_info[candidate_home] & ((1 << back) - 1);
+                (movable != 0).then(|| (candidate_home, movable.trailing_zeros() as usize, back))
+            });
+            let Some((candidate_home, offset, back)) = hop else {
+                return Err(entry);
+            };
+
+            let from = (candidate_home + offset) & mask;
+            self.slots[free] = self.slots[from].take();
+            self.hop_info[candidate_home] ^= (1 << offset) | (1 << back);
+            distance -= back - offset;
+        }
+
+        self.slots[(home + distance) & mask] = Some(entry);
+        self.hop_info[home] |= 1 << distance;
+        Ok(())
+    }
+
+    fn resize(&mut self, mut n_slots: usize, pending: Option<(K, V)>) {
+        let mut entries: Vec<(K, V)> = self.slots.drain(..).flatten().chain(pending).collect();
+
+        'retry: loop {
+            self.slots = (0..n_slots).map(|_| None).collect();
+            self.hop_info = vec![0; n_slots];
+            while let Some(entry) = entries.pop() {
+                if let Err(entry) = self.place(entry) {
+                    entries.push(entry);
+                    entries.extend(self.slots.drain(..).flatten());
+                    n_slots *= 2;
+                    continue 'retry;
+                }
+            }
+            break;
+        }
+    }
+
+    // Panics unless every home's bitmap marks exactly the slots holding its
+    // entries, all within `NEIGHBORHOOD` of it, and the item count matches.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let mut expected = vec![0u32; self.slots.len()];
+        let mut items = 0;
+        for (idx, slot) in self.slots.iter().enumerate() {
+            let Some((key, _)) = slot else { continue };
+            items += 1;
+            let home = self.home_idx(key);
+            let distance = idx.wrapping_sub(home) & self.mask();
+            assert!(distance < NEIGHBORHOOD, "entry too far from home");
+            expected[home] |= 1 << distance;
+        }
+        assert_eq!(expected, self.hop_info, "bitmaps do not match slots");
+        assert_eq!(items, self.items, "item count is off");
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(idx) = self.find(&key) {
+            let (_, evalue) = self.slots[idx].as_mut()?;
+            return Some(mem::replace(evalue, value));
+        }
+
+        if self.slots.is_empty() || 10 * (self.items + 1) > 9 * self.slots.len() {
+            self.resize((2 * self.slots.len()).max(MIN_SLOTS), None);
+        }
+
+        if let Err(entry) = self.place((key, value)) {
+            self.resize(2 * self.slots.len(), Some(entry));
+        }
+        self.items += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.find(key)?;
+        self.slots[idx].as_ref().map(|(_, value)| value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.find(key)?;
+        let home = self.home_idx(key);
+        self.hop_info[home] &= !(1 << (idx.wrapping_sub(home) & self.mask()));
+        self.items -= 1;
+        self.slots[idx].take().map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.slots.iter().flatten().map(|(key, value)| (key, value))
+    }
+}
+
+impl<K, V> Default for HopscotchHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = HopscotchHashMap::new();
+        assert_eq!(map.get("foo"), None);
+        for i in 0..1000 {
+            assert_eq!(map.insert(i.to_string(), i), None);
+        }
+        assert_eq!(map.insert("7".to_string(), 70), Some(7));
+        assert_eq!(map.len(), 1000);
+        assert!(map.slots.len() * 9 >= map.len() * 10);
+        for i in 0..1000 {
+            let expected = if i == 7 { 70 } else { i };
+            assert_eq!(map.get(i.to_string().as_str()), Some(&expected));
+        }
+        assert_eq!(map.iter().count(), 1000);
+        for i in 0..500 {
+            assert_eq!(
+                map.remove(i.to_string().as_str()),
+                Some(if i == 7 { 70 } else { i })
+            );
+        }
+        assert!(!map.contains_key("7"));
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get("999"), Some(&999));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn maps_are_seeded_independently() {
+        let homes = || {
+            let mut map = HopscotchHashMap::new();
+            map.insert(0, 0);
+            (0..64).map(|key| map.home_idx(&key)).collect::<Vec<_>>()
+        };
+        assert_ne!(homes(), homes());
+    }
+}
@@ -0,0 +1,224 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::RefCell;
+use core::hash::Hash;
+
+use crate::{hash_one, DefaultState, HashMap, HashValue};
+
+const SKETCH_WIDTH: usize = 1024;
+const SKETCH_DEPTH: usize = 4;
+const DEFAULT_TRACKED_KEYS: usize = 32;
+
+// Count-min sketch: every key bumps one counter per row, and the smallest of
+// its counters is an upper bound on how often it was seen.
+pub struct FrequencySketch {
+    counters: Vec<u64>,
+    width: usize,
+    depth: usize,
+    hash_builder: DefaultState,
+}
+
+impl FrequencySketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(
+            width > 0 && depth > 0,
+            "a sketch needs at least one counter"
+        );
+        FrequencySketch {
+            counters: vec![0; width * depth],
+            width,
+            depth,
+            hash_builder: DefaultState::default(),
+        }
+    }
+
+    fn counter_idx<Q: ?Sized + Hash>(&self, row: usize, key: &Q) -> usize {
+        let hash = hash_one(&self.hash_builder, &(row, key));
+        row * self.width + (hash % (self.width as HashValue)) as usize
+    }
+
+    pub fn record<Q: ?Sized + Hash>(&mut self, key: &Q) -> u64 {
+        (0..self.depth)
+            .map(|row| {
+                let i = self.counter_idx(row, key);
+                self.counters[i] += 1;
+                self.counters[i]
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn estimate<Q: ?Sized + Hash>(&self, key: &Q) -> u64 {
+        (0..self.depth)
+            .map(|row| self.counters[self.counter_idx(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+struct HotKeys<K> {
+    sketch: FrequencySketch,
+    candidates: Vec<(K, u64)>,
+    capacity: usize,
+}
+
+impl<K: Eq + Hash + Clone> HotKeys<K> {
+    fn record(&mut self, key: &K) {
+        let count = self.sketch.record(key);
+        if let Some(candidate) = self.candidates.iter_mut().find(|(k, _)| k == key) {
+            candidate.1 = count;
+        } else if self.candidates.len() < self.capacity {
+            self.candidates.push((key.clone(), count));
+        } else if let Some(coldest) = self.candidates.iter_mut().min_by_key(|(_, c)| *c) {
+            if coldest.1 < count {
+                *coldest = (key.clone(), count);
+            }
+        }
+    }
+}
+
+pub struct HotKeyHashMap<K, V> {
+    map: HashMap<K, V>,
+    hot_keys: RefCell<HotKeys<K>>,
+}
+
+impl<K, V> HotKeyHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_tracked_keys(DEFAULT_TRACKED_KEYS)
+    }
+
+    pub fn with_tracked_keys(capacity: usize) -> Self {
+        HotKeyHashMap {
+            map: HashMap::new(),
+            hot_keys: RefCell::new(HotKeys {
+                sketch: FrequencySketch::new(SKETCH_WIDTH, SKETCH_DEPTH),
+                candidates: Vec::with_capacity(capacity),
+                capacity,
+            }),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    // Records the stored key, which `Borrow` guarantees hashes like `key`,
+    // so any borrowed form of the key can be used to look it up.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + Clone,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (stored, value) = self.map.get_key_value(key)?;
+        self.hot_keys.borrow_mut().record(stored);
+        Some(value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let value = self.map.remove(key)?;
+        self.hot_keys
+            .get_mut()
+            .candidates
+            .retain(|(k, _)| k.borrow() != key);
+        Some(value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn access_count<Q>(&self, key: &Q) -> u64
+    where
+        Q: ?Sized + Hash,
+    {
+        self.hot_keys.borrow().sketch.estimate(key)
+    }
+
+    // Hottest first. Only as many keys as `with_tracked_keys` was given (32
+    // for `new`) are tracked, so a larger `n` returns no more than that.
+    pub fn hot_keys(&self, n: usize) -> Vec<(K, u64)>
+    where
+        K: Clone,
+    {
+        let mut hot = self.hot_keys.borrow().candidates.clone();
+        hot.sort_by(|(_, a), (_, b)| b.cmp(a));
+        hot.truncate(n);
+        hot
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V> Default for HotKeyHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::boxed::Box;
+
+    #[test]
+    fn reports_most_accessed_keys() {
+        let mut map = HotKeyHashMap::with_tracked_keys(4);
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        for i in 0..100 {
+            for _ in 0..(i % 10) {
+                map.get(&i);
+            }
+        }
+        for _ in 0..50 {
+            map.get(&7);
+            map.get(&42);
+        }
+
+        let hot = map.hot_keys(2);
+        assert_eq!(hot.len(), 2);
+        assert!(hot.iter().any(|(k, _)| *k == 7));
+        assert!(hot.iter().any(|(k, _)| *k == 42));
+        assert!(map.access_count(&42) >= 52);
+
+        map.remove(&42);
+        assert!(map.hot_keys(4).iter().all(|(k, _)| *k != 42));
+        assert_eq!(map.hot_keys(100).len(), 3);
+    }
+
+    #[test]
+    fn borrowed_keys_count_towards_the_stored_key() {
+        let mut map: HotKeyHashMap<Box<str>, u32> = HotKeyHashMap::new();
+        map.insert("a".into(), 1);
+        map.insert("b".into(), 2);
+        for _ in 0..3 {
+            assert_eq!(map.get("a"), Some(&1));
+        }
+        assert_eq!(map.get("b"), Some(&2));
+
+        assert_eq!(map.hot_keys(1), [("a".into(), 3)]);
+        assert_eq!(map.access_count("a"), 3);
+    }
+}
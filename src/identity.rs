@@ -0,0 +1,159 @@
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+
+use crate::HashMap;
+
+// Pointer-like keys compared by the address they point at rather than by the
+// value behind them.
+pub trait PtrKey {
+    type Target: ?Sized;
+
+    fn target_ptr(&self) -> *const Self::Target;
+}
+
+impl<T: ?Sized> PtrKey for Rc<T> {
+    type Target = T;
+
+    fn target_ptr(&self) -> *const T {
+        Rc::as_ptr(self)
+    }
+}
+
+impl<T: ?Sized> PtrKey for Arc<T> {
+    type Target = T;
+
+    fn target_ptr(&self) -> *const T {
+        Arc::as_ptr(self)
+    }
+}
+
+impl<T: ?Sized> PtrKey for &T {
+    type Target = T;
+
+    fn target_ptr(&self) -> *const T {
+        *self
+    }
+}
+
+// Metadata of wide pointers (vtables, lengths) is dropped, so only the data
+// address takes part in hashing and equality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Address(*const ());
+
+// SAFETY: the address is only compared and hashed, never dereferenced.
+unsafe impl Send for Address {}
+unsafe impl Sync for Address {}
+
+impl Address {
+    fn of<T: ?Sized>(ptr: *const T) -> Self {
+        Address(ptr as *const ())
+    }
+}
+
+struct ByAddress<P> {
+    address: Address,
+    ptr: P,
+}
+
+impl<P> Hash for ByAddress<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address.hash(state);
+    }
+}
+
+impl<P> PartialEq for ByAddress<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<P> Eq for ByAddress<P> {}
+
+impl<P> Borrow<Address> for ByAddress<P> {
+    fn borrow(&self) -> &Address {
+        &self.address
+    }
+}
+
+pub struct PtrIdentityMap<P, V> {
+    map: HashMap<ByAddress<P>, V>,
+}
+
+impl<P, V> PtrIdentityMap<P, V>
+where
+    P: PtrKey,
+{
+    pub fn new() -> Self {
+        PtrIdentityMap {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: P, value: V) -> Option<V> {
+        let address = Address::of(key.target_ptr());
+        self.map.insert(ByAddress { address, ptr: key }, value)
+    }
+
+    pub fn get(&self, target: &P::Target) -> Option<&V> {
+        self.map.get(&Address::of(target))
+    }
+
+    pub fn contains_key(&self, target: &P::Target) -> bool {
+        self.map.contains_key(&Address::of(target))
+    }
+
+    pub fn remove(&mut self, target: &P::Target) -> Option<V> {
+        self.map.remove(&Address::of(target))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&P, &V)> {
+        self.map.iter().map(|(key, value)| (&key.ptr, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<P, V> Default for PtrIdentityMap<P, V>
+where
+    P: PtrKey,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn equal_values_at_different_addresses_are_distinct() {
+        let a = Rc::new("node".to_string());
+        let b = Rc::new("node".to_string());
+        let mut map = PtrIdentityMap::new();
+        assert_eq!(map.insert(a.clone(), 1), None);
+        assert_eq!(map.insert(b.clone(), 2), None);
+        assert_eq!(map.insert(a.clone(), 10), Some(1));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&a), Some(&10));
+        assert_eq!(map.get(&*b), Some(&2));
+        assert!(!map.contains_key(&"node".to_string()));
+        assert_eq!(map.remove(&b), Some(2));
+        assert!(map.iter().all(|(key, _)| Rc::ptr_eq(key, &a)));
+
+        let slice: &[u8] = &[1, 2, 3];
+        let mut by_ref = PtrIdentityMap::new();
+        by_ref.insert(slice, "bytes");
+        assert_eq!(by_ref.get(slice), Some(&"bytes"));
+    }
+}
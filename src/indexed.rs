@@ -0,0 +1,382 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+use core::slice;
+
+use crate::{
+    capacity_overflow, empty_slots, find_slot_by, hash_one, place_slot, take_slot, DefaultState,
+    Growth, HashValue, LoadFactor, Slot, DEFAULT_MAX_LOAD,
+};
+
+// Entries live in insertion order in a dense `Vec`. The Robin Hood table
+// only maps each key's hash to its position there. Both the entries and the
+// table's slots keep the hash, so neither growing the table nor moving an
+// entry ever hashes a key again.
+pub struct IndexHashMap<K, V, S = DefaultState> {
+    entries: Vec<Bucket<K, V>>,
+    indices: Vec<Option<Slot<(), usize>>>,
+    hash_builder: S,
+}
+
+struct Bucket<K, V> {
+    hash: HashValue,
+    key: K,
+    value: V,
+}
+
+impl<K, V> IndexHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultState::default())
+    }
+}
+
+impl<K, V, S> IndexHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        IndexHashMap {
+            entries: Vec::new(),
+            indices: Vec::new(),
+            hash_builder,
+        }
+    }
+
+    // Sized like a `HashMap` with the default load factor and growth.
+    pub fn reserve(&mut self, additional: usize) {
+        let items = self
+            .entries
+            .len()
+            .checked_add(additional)
+            .unwrap_or_else(|| capacity_overflow());
+        self.entries.reserve(additional);
+        let growth = Growth::default();
+        let target_size = LoadFactor::new(DEFAULT_MAX_LOAD).min_slots_for(items, growth);
+        if target_size <= self.indices.len() {
+            return;
+        }
+        let n_slots = target_size.max(growth.next_size(self.indices.len()));
+        let old_indices = mem::replace(&mut self.indices, empty_slots(n_slots));
+        for slot in old_indices.into_iter().flatten() {
+            place_slot(&mut self.indices, slot.hash, (), slot.value);
+        }
+    }
+
+    fn find_slot<Q>(&self, hash: HashValue, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        if self.indices.is_empty() {
+            return None;
+        }
+        find_slot_by(&self.indices, hash, |_, &idx| {
+            self.entries[idx].key.borrow() == key
+        })
+    }
+
+    // Index into `entries` that a found slot points at.
+    fn index_at(&self, slot: usize) -> usize {
+        self.indices[slot]
+            .as_ref()
+            .expect("found slot is occupied")
+            .value
+    }
+
+    // Replacing the value of a present key keeps its position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = hash_one(&self.hash_builder, &key);
+        if let Some(slot) = self.find_slot(hash, &key) {
+            let idx = self.index_at(slot);
+            return Some(mem::replace(&mut self.entries[idx].value, value));
+        }
+
+        self.reserve(1);
+        place_slot(&mut self.indices, hash, (), self.entries.len());
+        self.entries.push(Bucket { hash, key, value });
+        None
+    }
+
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let slot = self.find_slot(hash_one(&self.hash_builder, key), key)?;
+        Some(self.index_at(slot))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.get_index_of(key)?;
+        Some(&self.entries[idx].value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.get_index_of(key)?;
+        Some(&mut self.entries[idx].value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_index_of(key).is_some()
+    }
+
+    fn take_index<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let slot = self.find_slot(hash_one(&self.hash_builder, key), key)?;
+        Some(take_slot(&mut self.indices, slot).1)
+    }
+
+    // O(1): the last entry takes the removed one's place, so the order of
+    // the remaining entries changes.
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.take_index(key)?;
+        let removed = self.entries.swap_remove(idx);
+        if let Some(moved) = self.entries.get(idx) {
+            let moved_from = self.entries.len();
+            let slot = find_slot_by(&self.indices, moved.hash, |_, &i| i == moved_from)
+                .expect("moved entry is indexed");
+            self.indices[slot]
+                .as_mut()
+                .expect("found slot is occupied")
+                .value = idx;
+        }
+        Some(removed.value)
+    }
+
+    // Keeps the order of the remaining entries, at the cost of shifting all
+    // the ones after the removed entry: O(n).
+    pub fn shift_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.take_index(key)?;
+        let removed = self.entries.remove(idx);
+        for slot in self.indices.iter_mut().flatten() {
+            if slot.value > idx {
+                slot.value -= 1;
+            }
+        }
+        Some(removed.value)
+    }
+}
+
+impl<K, V, S> IndexHashMap<K, V, S> {
+    pub fn get_index(&self, idx: usize) -> Option<(&K, &V)> {
+        let bucket = self.entries.get(idx)?;
+        Some((&bucket.key, &bucket.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            entries: self.entries.iter(),
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    entries: slice::Iter<'a, Bucket<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next()
+            .map(|bucket| (&bucket.key, &bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next_back()
+            .map(|bucket| (&bucket.key, &bucket.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K, V, S> IntoIterator for &'a IndexHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<K, V> {
+    entries: vec::IntoIter<Bucket<K, V>>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|bucket| (bucket.key, bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next_back()
+            .map(|bucket| (bucket.key, bucket.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V, S> IntoIterator for IndexHashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            entries: self.entries.into_iter(),
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for IndexHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = IndexHashMap::with_hasher(S::default());
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for IndexHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> Default for IndexHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> fmt::Debug for IndexHashMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Counted;
+    use core::cell::Cell;
+    use std::format;
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut map: IndexHashMap<_, _> = (0..100).map(|i| (i, i * 10)).collect();
+        assert_eq!(map.insert(5, 0), Some(50));
+        assert!(map.iter().map(|(&key, _)| key).eq(0..100));
+        assert_eq!(map.get_index(5), Some((&5, &0)));
+        assert_eq!(map.get_index_of(&42), Some(42));
+        assert_eq!(map.get_index(100), None);
+
+        assert_eq!(map.swap_remove(&10), Some(100));
+        assert_eq!(map.get_index(10), Some((&99, &990)));
+        assert_eq!(map.get(&99), Some(&990));
+        assert_eq!(map.shift_remove(&0), Some(0));
+        assert_eq!(map.get_index_of(&99), Some(9));
+        assert_eq!(map.get_index_of(&98), Some(97));
+        assert_eq!(map.swap_remove(&0), None);
+        assert_eq!(map.len(), 98);
+        assert!(map.iter().all(|(key, value)| map.get(key) == Some(value)));
+        assert_eq!(format!("{:?}", map.into_iter().next()), "Some((1, 10))");
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_panics_instead_of_wrapping() {
+        let mut map = IndexHashMap::new();
+        map.insert(1, 1);
+        map.reserve(usize::MAX);
+    }
+
+    #[test]
+    fn sizes_like_hash_map_and_never_rehashes_moved_entries() {
+        let hashes = Cell::new(0);
+        let counted = |id| Counted::new(id, &hashes);
+        let mut map = IndexHashMap::new();
+        let mut plain = crate::HashMap::new();
+        for id in 0..1000 {
+            map.insert(counted(id), id);
+            plain.insert(id, id);
+        }
+        assert_eq!(map.indices.len(), plain.slots.len());
+
+        hashes.set(0);
+        assert_eq!(map.swap_remove(&counted(0)), Some(0));
+        assert_eq!(hashes.get(), 1);
+        assert_eq!(map.get_index_of(&counted(999)), Some(0));
+    }
+}
@@ -0,0 +1,177 @@
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::cell::{Cell, OnceCell};
+use core::hash::Hash;
+
+use crate::HashMap;
+
+type Thunk<V> = Box<dyn FnOnce() -> V>;
+
+struct LazyValue<V> {
+    value: OnceCell<V>,
+    init: Cell<Option<Thunk<V>>>,
+}
+
+// The initializer is taken out before it runs, so a value with neither a
+// result nor an initializer is one whose initializer panicked. It stays
+// poisoned, since an `FnOnce` cannot be retried.
+const POISONED: &str = "lazy value's initializer panicked on an earlier access";
+
+impl<V> LazyValue<V> {
+    fn force(&self) -> &V {
+        self.value.get_or_init(|| {
+            let init = self.init.take().expect(POISONED);
+            init()
+        })
+    }
+
+    fn into_value(self) -> V {
+        match self.value.into_inner() {
+            Some(value) => value,
+            None => {
+                let init = self.init.into_inner().expect(POISONED);
+                init()
+            }
+        }
+    }
+}
+
+pub struct LazyMap<K, V> {
+    map: HashMap<K, LazyValue<V>>,
+}
+
+impl<K, V> LazyMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        LazyMap {
+            map: HashMap::new(),
+        }
+    }
+
+    // Like `remove`, a replaced value that was never read is computed so it
+    // can be returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map
+            .insert(
+                key,
+                LazyValue {
+                    value: OnceCell::from(value),
+                    init: Cell::new(None),
+                },
+            )
+            .map(LazyValue::into_value)
+    }
+
+    pub fn insert_with<F>(&mut self, key: K, init: F) -> Option<V>
+    where
+        F: FnOnce() -> V + 'static,
+    {
+        self.map
+            .insert(
+                key,
+                LazyValue {
+                    value: OnceCell::new(),
+                    init: Cell::new(Some(Box::new(init))),
+                },
+            )
+            .map(LazyValue::into_value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.get(key).map(LazyValue::force)
+    }
+
+    pub fn is_evaluated<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map
+            .get(key)
+            .is_some_and(|lazy| lazy.value.get().is_some())
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.remove(key).map(LazyValue::into_value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V> Default for LazyMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::AssertUnwindSafe;
+    use std::rc::Rc;
+
+    #[test]
+    fn values_are_computed_once_on_first_get() {
+        let calls = Rc::new(Cell::new(0));
+        let mut map = LazyMap::new();
+        let counter = calls.clone();
+        map.insert_with("expensive", move || {
+            counter.set(counter.get() + 1);
+            42
+        });
+        map.insert("cheap", 1);
+
+        assert!(!map.is_evaluated("expensive"));
+        assert_eq!(calls.get(), 0);
+        assert_eq!(map.get("expensive"), Some(&42));
+        assert_eq!(map.get("expensive"), Some(&42));
+        assert_eq!(calls.get(), 1);
+        assert!(map.is_evaluated("expensive"));
+        assert_eq!(map.get("cheap"), Some(&1));
+
+        map.insert_with("never read", || 7);
+        assert_eq!(map.remove("never read"), Some(7));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.insert("cheap", 2), Some(1));
+        assert_eq!(map.insert_with("cheap", || 3), Some(2));
+        assert_eq!(map.insert("cheap", 4), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "panicked on an earlier access")]
+    fn a_panicking_initializer_poisons_its_entry() {
+        let mut map = LazyMap::new();
+        map.insert_with("broken", || -> i32 { panic!("initializer failed") });
+        let first = std::panic::catch_unwind(AssertUnwindSafe(|| map.get("broken").copied()));
+        assert!(first.is_err());
+        map.get("broken");
+    }
+}
@@ -1,12 +1,353 @@
-use std::borrow::Borrow;
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::mem;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+pub mod append_only;
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod concurrent;
+pub mod cuckoo;
+pub mod diff;
+pub mod entry;
+pub mod float;
+pub mod fnv;
+pub mod hopscotch;
+pub mod hot;
+pub mod identity;
+pub mod indexed;
+pub mod lazy;
+#[cfg(feature = "std")]
+pub mod lock;
+#[cfg(feature = "std")]
+pub mod metadata;
+pub mod normalized;
+pub mod observed;
+pub mod persistent;
+pub mod priority;
+pub mod probing;
+pub mod ring;
+pub mod scoped;
+pub mod set;
+pub mod table;
+pub mod transactional;
+pub mod two_choice;
+pub mod view;
+pub mod weak;
+
+#[cfg(test)]
+mod testing;
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::hash::RandomState;
+
+use core::iter::{self, Chain};
+use core::mem;
+use core::ops::Index;
+use core::slice;
 
 const INITIAL_N_BUCKETS: usize = 1;
 
-pub struct HashMap<K, V> {
-    buckets: Vec<Vec<(K, V)>>,
+#[inline]
+fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: prefetching is only a hint and never faults, whatever the address.
+    unsafe {
+        core::arch::x86_64::_mm_prefetch::<{ core::arch::x86_64::_MM_HINT_T0 }>(ptr as *const i8);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+// With the `hash32` feature, hashes are folded to 32 bits as soon as they are
+// computed, so all stored hashes and bucket arithmetic are 32-bit too.
+#[cfg(not(feature = "hash32"))]
+pub(crate) type HashValue = u64;
+#[cfg(feature = "hash32")]
+pub(crate) type HashValue = u32;
+
+// Twice as wide as a hash, so a hash times a table size never overflows.
+#[cfg(not(feature = "hash32"))]
+pub(crate) type WideHash = u128;
+#[cfg(feature = "hash32")]
+pub(crate) type WideHash = u64;
+
+pub(crate) fn fold_hash(hash: u64) -> HashValue {
+    #[cfg(feature = "hash32")]
+    let hash = (hash ^ (hash >> 32)) as u32;
+    hash
+}
+
+// What `HashMap::new` hashes with. `RandomState` seeds itself from the OS,
+// so without `std` maps fall back to FNV-1a; `with_hasher` takes any other.
+#[cfg(feature = "std")]
+pub type DefaultState = RandomState;
+#[cfg(not(feature = "std"))]
+pub type DefaultState = fnv::FnvBuildHasher;
+
+// Every hash in the crate is computed here, in one shot from the map's
+// builder, so seeding or caching only ever has to change in one place.
+pub(crate) fn hash_one<S, T>(hash_builder: &S, value: &T) -> HashValue
+where
+    S: BuildHasher,
+    T: ?Sized + Hash,
+{
+    fold_hash(hash_builder.hash_one(value))
+}
+
+// 2^BITS divided by the golden ratio. Multiplying by it smears every input
+// bit into the high bits, which is where bucket indices are taken from.
+#[cfg(not(feature = "hash32"))]
+const FIBONACCI: HashValue = 0x9e37_79b9_7f4a_7c15;
+#[cfg(feature = "hash32")]
+const FIBONACCI: HashValue = 0x9e37_79b9;
+
+// Maps a hash onto one of `n_buckets` buckets by scaling the mixed hash down
+// to `[0, n_buckets)`, which amounts to taking its top bits when the count is
+// a power of two. Basing the index on the high bits keeps weak hashes, such
+// as sequential IDs hashed to themselves, from clustering in a few buckets.
+fn bucket_for(hash: HashValue, n_buckets: usize) -> usize {
+    ((mix(hash) as WideHash * n_buckets as WideHash) >> HashValue::BITS) as usize
+}
+
+fn mix(hash: HashValue) -> HashValue {
+    hash.wrapping_mul(FIBONACCI)
+}
+
+// An odd multiplier unrelated to `FIBONACCI`, for splitting keys between
+// maps rather than between buckets.
+#[cfg(not(feature = "hash32"))]
+const PART_MIX: HashValue = 0xbf58_476d_1ce4_e5b9;
+#[cfg(feature = "hash32")]
+const PART_MIX: HashValue = 0x85eb_ca6b;
+
+// Picks which of `n_parts` maps a hash belongs to. Weak hashes still spread
+// evenly, and since the multiplier differs from `mix`, the keys that share a
+// part fill all of its buckets instead of the band `bucket_for` would give
+// them.
+pub(crate) fn part_for(hash: HashValue, n_parts: usize) -> usize {
+    ((hash.wrapping_mul(PART_MIX) as WideHash * n_parts as WideHash) >> HashValue::BITS) as usize
+}
+
+fn next_prime(n: usize) -> usize {
+    let is_prime = |n: usize| {
+        n >= 2
+            && (2..)
+                .take_while(|d| d * d <= n)
+                .all(|d| !n.is_multiple_of(d))
+    };
+    (n..)
+        .find(|&n| is_prime(n))
+        .expect("bucket counts stay far below usize::MAX")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrowthFactor {
+    #[default]
+    Double,
+    // Rehashes more often but never holds more than half again the buckets
+    // it needs.
+    OneAndHalf,
+}
+
+// Load factors in 1/65536ths, so that sizing stays in integer math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct LoadFactor(u32);
+
+impl LoadFactor {
+    const ONE: u64 = 1 << 16;
+
+    fn new(load_factor: f32) -> Self {
+        LoadFactor((load_factor * Self::ONE as f32) as u32)
+    }
+
+    // Number of slots filled at this load in a table of `n_slots`.
+    fn of(self, n_slots: usize) -> usize {
+        ((n_slots as WideHash * self.0 as WideHash) >> 16) as usize
+    }
+
+    // Smallest slot count allowed by `growth` that holds `items` without
+    // going past this load, and with a slot to spare.
+    fn min_slots_for(self, items: usize, growth: Growth) -> usize {
+        match items {
+            0 => 0,
+            n => {
+                let at_max_load = (n as WideHash - 1)
+                    .checked_mul(LoadFactor::ONE as WideHash)
+                    .and_then(|scaled| usize::try_from(scaled.div_ceil(self.0 as WideHash)).ok())
+                    .unwrap_or_else(|| capacity_overflow());
+                let with_spare = n.checked_add(1).unwrap_or_else(|| capacity_overflow());
+                growth.round_size(at_max_load.max(with_spare))
+            }
+        }
+    }
+}
+
+// Panics like std's collections do when a requested size cannot be
+// represented, rather than letting the arithmetic wrap to a small table.
+fn capacity_overflow() -> ! {
+    panic!("capacity overflow")
+}
+
+const DEFAULT_MAX_LOAD: f32 = 0.75;
+const DEFAULT_MIN_LOAD: f32 = 0.125;
+
+// How the bucket count grows once the load factor is crossed. With
+// `prime_sizes` every bucket count is rounded up to a prime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Growth {
+    pub factor: GrowthFactor,
+    pub prime_sizes: bool,
+}
+
+impl Growth {
+    fn next_size(self, n_buckets: usize) -> usize {
+        let grown = match (n_buckets, self.factor) {
+            (0, _) => Some(INITIAL_N_BUCKETS),
+            (n, GrowthFactor::Double) => n.checked_mul(2),
+            (n, GrowthFactor::OneAndHalf) => n.checked_add(n.div_ceil(2)),
+        };
+        self.round_size(grown.unwrap_or_else(|| capacity_overflow()))
+    }
+
+    // Smallest allowed bucket count of at least `n_buckets`.
+    fn round_size(self, n_buckets: usize) -> usize {
+        match (n_buckets, self.prime_sizes, self.factor) {
+            (0, _, _) => 0,
+            (n, true, _) => next_prime(n),
+            (n, false, GrowthFactor::Double) => n
+                .checked_next_power_of_two()
+                .unwrap_or_else(|| capacity_overflow()),
+            (n, false, GrowthFactor::OneAndHalf) => n,
+        }
+    }
+}
+
+// One entry of the flat table, stored in the slot `distance` steps past its
+// home slot. The key's hash is kept so that probes can skip keys with a
+// different hash without calling `Eq`, and resizes never hash a key again.
+#[derive(Clone)]
+struct Slot<K, V> {
+    distance: usize,
+    hash: HashValue,
+    key: K,
+    value: V,
+}
+
+type Slots<K, V> = [Option<Slot<K, V>>];
+
+// The table uses Robin Hood linear probing: along every run of occupied
+// slots, entries are ordered by home slot, which is what lets lookups stop
+// at the first entry closer to its home than the key being searched for
+// would be. The table is never allowed to fill up, so probes always end.
+//
+// The probe sequence is fixed. Removal shifts the rest of a run back by one
+// slot instead of leaving a tombstone, and `scan` and the removing iterators
+// walk runs slot by slot; all of that relies on an entry's probe sequence
+// being the slots right after its home. `ProbingHashMap` takes a
+// `ProbeStrategy` instead, at the cost of tombstones.
+fn find_slot<K, V, Q>(slots: &Slots<K, V>, hash: HashValue, key: &Q) -> Option<usize>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Eq,
+{
+    find_slot_by(slots, hash, |slot_key, _| slot_key.borrow() == key)
+}
+
+// Like `find_slot`, for tables whose slots only point at where the key is.
+// `is_match` only runs for slots holding the same hash.
+fn find_slot_by<K, V>(
+    slots: &Slots<K, V>,
+    hash: HashValue,
+    mut is_match: impl FnMut(&K, &V) -> bool,
+) -> Option<usize> {
+    let mut idx = bucket_for(hash, slots.len());
+    for distance in 0.. {
+        match &slots[idx] {
+            Some(slot) if slot.distance >= distance => {
+                if slot.distance == distance
+                    && slot.hash == hash
+                    && is_match(&slot.key, &slot.value)
+                {
+                    return Some(idx);
+                }
+            }
+            _ => return None,
+        }
+        idx = (idx + 1) % slots.len();
+    }
+    unreachable!("probing only stops at a match or a miss")
+}
+
+// Places a key known to be absent, taking the slot of any entry that sits
+// closer to its home and carrying that entry on instead. Returns the slot
+// the new key ended up in.
+fn place_slot<K, V>(slots: &mut Slots<K, V>, hash: HashValue, key: K, value: V) -> usize {
+    let mut carried = Slot {
+        distance: 0,
+        hash,
+        key,
+        value,
+    };
+    let mut placed = None;
+    let mut idx = bucket_for(hash, slots.len());
+    loop {
+        let slot = &mut slots[idx];
+        match slot {
+            None => {
+                *slot = Some(carried);
+                return placed.unwrap_or(idx);
+            }
+            Some(resident) if resident.distance < carried.distance => {
+                mem::swap(resident, &mut carried);
+                placed.get_or_insert(idx);
+            }
+            Some(_) => {}
+        }
+        idx = (idx + 1) % slots.len();
+        carried.distance += 1;
+    }
+}
+
+// Empties a slot and shifts the entries probing past it one slot back, so no
+// tombstones are needed.
+fn take_slot<K, V>(slots: &mut Slots<K, V>, idx: usize) -> (K, V) {
+    let removed = slots[idx].take().expect("taken slot is occupied");
+    let mut hole = idx;
+    loop {
+        let next = (hole + 1) % slots.len();
+        match &mut slots[next] {
+            Some(slot) if slot.distance > 0 => slot.distance -= 1,
+            _ => break,
+        }
+        slots[hole] = slots[next].take();
+        hole = next;
+    }
+    (removed.key, removed.value)
+}
+
+fn empty_slots<K, V>(n_slots: usize) -> Vec<Option<Slot<K, V>>> {
+    iter::repeat_with(|| None).take(n_slots).collect()
+}
+
+#[derive(Clone)]
+pub struct HashMap<K, V, S = DefaultState> {
+    slots: Vec<Option<Slot<K, V>>>,
     items: usize,
+    hash_builder: S,
+    growth: Growth,
+    max_load: LoadFactor,
+    min_load: LoadFactor,
+    // Slot that iteration starts from.
+    iter_offset: usize,
 }
 
 impl<K, V> HashMap<K, V>
@@ -14,47 +355,166 @@ where
     K: Hash + Eq,
 {
     pub fn new() -> Self {
+        Self::with_hasher(DefaultState::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultState::default())
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
         HashMap {
-            buckets: Vec::new(),
+            slots: Vec::new(),
             items: 0,
+            hash_builder,
+            growth: Growth::default(),
+            max_load: LoadFactor::new(DEFAULT_MAX_LOAD),
+            min_load: LoadFactor::new(DEFAULT_MIN_LOAD),
+            iter_offset: 0,
         }
+        .shuffled_iteration(cfg!(debug_assertions))
     }
 
-    fn resize(&mut self) {
-        let target_size = match self.buckets.len() {
-            0 => INITIAL_N_BUCKETS,
-            n => 2 * n,
-        };
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let mut map = Self::with_hasher(hash_builder);
+        map.reserve_exact(capacity);
+        map
+    }
 
-        let mut new_buckets = Vec::with_capacity(target_size);
-        new_buckets.extend((0..target_size).map(|_| Vec::new()));
-        for (key, value) in self.buckets.iter_mut().flat_map(|bucket| bucket.drain(..)) {
-            let mut hasher = DefaultHasher::new();
-            key.hash(&mut hasher);
-            let bucket_idx = (hasher.finish() % (new_buckets.len() as u64)) as usize;
-            new_buckets[bucket_idx].push((key, value));
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    // Starts iteration at a random slot picked per map, like Go does, so
+    // code that leans on iteration order breaks in tests rather than after
+    // the layout changes. On by default in debug builds.
+    pub fn shuffled_iteration(mut self, enabled: bool) -> Self {
+        if !enabled {
+            self.iter_offset = 0;
+            return self;
         }
+        // Without `std` there is no entropy to draw on, so the offset comes
+        // from the map's own hasher instead.
+        #[cfg(feature = "std")]
+        let seed = RandomState::new().hash_one(());
+        #[cfg(not(feature = "std"))]
+        let seed = self.hash_builder.hash_one(());
+        self.iter_offset = seed as usize;
+        self
+    }
 
-        self.buckets = new_buckets;
+    pub fn with_growth(mut self, growth: Growth) -> Self {
+        self.growth = growth;
+        self
     }
 
-    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        if self.buckets.is_empty() || self.items > 3 * self.buckets.len() / 4 {
-            self.resize();
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    // The table grows when an insert would take it past this fraction of
+    // its slots. Defaults to 3/4; higher saves memory, lower keeps probe
+    // runs short.
+    pub fn max_load_factor(mut self, load_factor: f32) -> Self {
+        assert!(
+            load_factor > 0.0 && load_factor <= 1.0,
+            "max load factor must be in (0, 1]"
+        );
+        self.max_load = LoadFactor::new(load_factor).max(LoadFactor(1));
+        self
+    }
+
+    // Removals shrink the table once fewer than this fraction of its slots
+    // are in use. Defaults to 1/8; zero turns shrinking off.
+    pub fn shrink_below(mut self, load_factor: f32) -> Self {
+        assert!(
+            (0.0..1.0).contains(&load_factor),
+            "shrink threshold must be in [0, 1)"
+        );
+        self.min_load = LoadFactor::new(load_factor);
+        self
+    }
+
+    fn resize_to(&mut self, target_size: usize) {
+        let old_slots = mem::replace(&mut self.slots, empty_slots(target_size));
+        for slot in old_slots.into_iter().flatten() {
+            place_slot(&mut self.slots, slot.hash, slot.key, slot.value);
         }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_slot(key, value).1
+    }
+
+    // Like `insert`, but hands back the stored value for further mutation
+    // instead of the one it replaced.
+    pub fn insert_mut(&mut self, key: K, value: V) -> &mut V {
+        self.insert_slot(key, value).0
+    }
 
-        let bucket_idx = self.bucket_idx(&key);
-        let bucket = &mut self.buckets[bucket_idx];
+    // Skips the search for an existing entry. If `key` is in fact present,
+    // the map holds it twice and which copy lookups find is unspecified;
+    // nothing worse than that happens.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) -> &mut V {
+        self.reserve(1);
 
-        for &mut (ref ekey, ref mut evalue) in bucket.iter_mut() {
-            if ekey == &key {
-                return Some(mem::replace(evalue, value));
+        let hash = self.hash(&key);
+        let idx = self.place_new(hash, key, value);
+        let slot = self.slots[idx].as_mut().expect("slot was just filled");
+        &mut slot.value
+    }
+
+    // Places a key known to be absent, once the caller has reserved room.
+    fn place_new(&mut self, hash: HashValue, key: K, value: V) -> usize {
+        self.items += 1;
+        place_slot(&mut self.slots, hash, key, value)
+    }
+
+    fn insert_slot(&mut self, key: K, value: V) -> (&mut V, Option<V>) {
+        let hash = self.hash(&key);
+        let (idx, old_value) = match self.find_hashed(hash, &key) {
+            Some(idx) => {
+                let slot = self.slots[idx].as_mut().expect("found slot is occupied");
+                (idx, Some(mem::replace(&mut slot.value, value)))
+            }
+            None => {
+                self.reserve(1);
+                (self.place_new(hash, key, value), None)
             }
+        };
+        let slot = self.slots[idx].as_mut().expect("slot was just filled");
+        (&mut slot.value, old_value)
+    }
+
+    // Like `find`, for a key the caller has already hashed. Only the
+    // vacant path needs room, so callers reserve after looking up, which
+    // keeps hits on a full table from growing it.
+    fn find_hashed<Q>(&self, hash: HashValue, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return None;
         }
+        find_slot(&self.slots, hash, key)
+    }
 
-        self.items += 1;
-        bucket.push((key, value));
-        None
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+        find_slot(&self.slots, self.hash(key), key)
     }
 
     pub fn get<Q>(&self, key: &Q) -> Option<&V>
@@ -62,11 +522,83 @@ where
         K: Borrow<Q>,
         Q: Eq + Hash + ?Sized,
     {
-        let bucket_idx = self.bucket_idx(key);
-        self.buckets[bucket_idx]
-            .iter()
-            .find(|(ekey, _)| ekey.borrow() == key)
-            .map(|(_, value)| value)
+        let idx = self.find(key)?;
+        self.slots[idx].as_ref().map(|slot| &slot.value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let idx = self.find(key)?;
+        self.slots[idx]
+            .as_ref()
+            .map(|slot| (&slot.key, &slot.value))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let idx = self.find(key)?;
+        self.slots[idx].as_mut().map(|slot| &mut slot.value)
+    }
+
+    pub fn get_many<Q, const N: usize>(&self, keys: [&Q; N]) -> [Option<&V>; N]
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.find_many(keys)
+            .map(|idx| self.slots[idx?].as_ref().map(|slot| &slot.value))
+    }
+
+    // Panics if two of the keys find the same entry. Keys that are missing
+    // may repeat.
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> [Option<&mut V>; N]
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let idxs = self.find_many(keys);
+        for (i, idx) in idxs.iter().enumerate() {
+            assert!(
+                idx.is_none() || !idxs[..i].contains(idx),
+                "get_many_mut called with the same key twice"
+            );
+        }
+
+        let slots = self.slots.as_mut_ptr();
+        idxs.map(|idx| {
+            // SAFETY: every index is in bounds, and no two are equal, so the
+            // references handed out never alias.
+            let slot = unsafe { &mut *slots.add(idx?) };
+            slot.as_mut().map(|slot| &mut slot.value)
+        })
+    }
+
+    // Hashes every key and prefetches the home slots before probing any of
+    // them, so the cache misses of a batch overlap instead of being paid one
+    // by one. `benches/get_many.rs` measures this against repeated `get`.
+    fn find_many<Q, const N: usize>(&self, keys: [&Q; N]) -> [Option<usize>; N]
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return [None; N];
+        }
+        let hashes = keys.map(|key| self.hash(key));
+        for &hash in &hashes {
+            prefetch(&self.slots[bucket_for(hash, self.slots.len())]);
+        }
+        let mut hashes = hashes.into_iter();
+        keys.map(|key| {
+            let hash = hashes.next().expect("one hash per key");
+            find_slot(&self.slots, hash, key)
+        })
     }
 
     pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
@@ -74,13 +606,54 @@ where
         K: Borrow<Q>,
         Q: ?Sized + Eq + Hash,
     {
-        let bucket_idx = self.bucket_idx(key);
-        let i = self.buckets[bucket_idx]
-            .iter()
-            .position(|(ekey, _)| ekey.borrow() == key)?;
-        let bucket = &mut self.buckets[bucket_idx];
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let entry = self.take(key)?;
+        self.shrink_if_sparse();
+        Some(entry)
+    }
+
+    fn take<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.find(key)?;
         self.items -= 1;
-        Some(bucket.swap_remove(i).1)
+        Some(take_slot(&mut self.slots, idx))
+    }
+
+    // Results line up with `keys`; a key listed twice only yields its value
+    // the first time. The table shrinks at most once, after the whole sweep.
+    pub fn remove_many<'a, Q>(&mut self, keys: impl IntoIterator<Item = &'a Q>) -> Vec<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: 'a + ?Sized + Eq + Hash,
+    {
+        let values = keys
+            .into_iter()
+            .map(|key| self.take(key).map(|(_, value)| value))
+            .collect();
+        self.shrink_if_sparse();
+        values
+    }
+
+    // Shrinks to the size that holds twice the remaining entries, so inserts
+    // right after a shrink do not immediately grow the table back.
+    fn shrink_if_sparse(&mut self) {
+        if self.items >= self.min_load.of(self.slots.len()) {
+            return;
+        }
+        let target_size = self.min_buckets_for(2 * self.items);
+        if target_size < self.slots.len() {
+            self.resize_to(target_size);
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -91,14 +664,12 @@ where
         self.items == 0
     }
 
-    fn bucket_idx<Q>(&self, key: &Q) -> usize
+    fn hash<Q>(&self, key: &Q) -> HashValue
     where
         K: Borrow<Q>,
-        Q: Eq + Hash + ?Sized,
+        Q: Hash + ?Sized,
     {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        (hasher.finish() % (self.buckets.len() as u64)) as usize
+        hash_one(&self.hash_builder, key)
     }
 
     pub fn contains_key<Q>(&self, key: &Q) -> bool
@@ -106,86 +677,1230 @@ where
         K: Borrow<Q>,
         Q: ?Sized + Eq + Hash,
     {
-        let bucket_idx = self.bucket_idx(key);
-        self.buckets[bucket_idx]
-            .iter()
-            .any(|(ekey, _)| ekey.borrow() == key)
+        self.find(key).is_some()
     }
-}
 
-pub struct HashIter<'a, K, V> {
-    map: &'a HashMap<K, V>,
-    current_bucket: usize,
-    current_item: usize,
-}
+    fn items_after(&self, additional: usize) -> usize {
+        self.items
+            .checked_add(additional)
+            .unwrap_or_else(|| capacity_overflow())
+    }
 
-impl<'a, K, V> HashIter<'a, K, V> {
-    pub fn new(hash_map: &'a HashMap<K, V>) -> Self {
-        Self {
-            map: hash_map,
-            current_bucket: 0,
-            current_item: 0,
+    // Smallest slot count allowed by the growth policy whose `capacity` is at
+    // least `items`.
+    fn min_buckets_for(&self, items: usize) -> usize {
+        self.max_load.min_slots_for(items, self.growth)
+    }
+
+    // Jumps straight to the needed slot count, but never grows by less than
+    // one regular step so that repeated small reserves stay amortized.
+    pub fn reserve(&mut self, additional: usize) {
+        let target_size = self.min_buckets_for(self.items_after(additional));
+        if target_size > self.slots.len() {
+            let step = self.growth.next_size(self.slots.len());
+            self.resize_to(target_size.max(step));
         }
     }
-}
 
-impl<'a, K, V> Iterator for HashIter<'a, K, V> {
-    type Item = (&'a K, &'a V);
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let target_size = self.min_buckets_for(self.items_after(additional));
+        if target_size > self.slots.len() {
+            self.resize_to(target_size);
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.map.buckets.get(self.current_bucket) {
-                Some(bucket) => match bucket.get(self.current_item) {
-                    Some((k, v)) => {
-                        self.current_item += 1;
-                        break Some((k, v));
-                    }
-                    None => {
-                        self.current_bucket += 1;
-                        self.current_item = 0;
-                        continue;
-                    }
-                },
-                None => break None,
-            }
+    // Number of entries the map holds before the next insert resizes it. The
+    // table grows once it is fuller than the max load factor, and always
+    // keeps at least one slot free.
+    pub fn capacity(&self) -> usize {
+        match self.slots.len() {
+            0 => 0,
+            n => (self.max_load.of(n) + 1).min(n - 1),
         }
     }
-}
 
-impl<'a, K, V> IntoIterator for &'a HashMap<K, V> {
-    type Item = (&'a K, &'a V);
-    type IntoIter = HashIter<'a, K, V>;
+    pub fn shrink_to_fit(&mut self) {
+        self.compact();
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        HashIter::new(self)
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.extract_if(|key, value| !f(key, value)).for_each(drop);
+        self.shrink_if_sparse();
+    }
+
+    // Empties the map even if the iterator is dropped early. The slots are
+    // kept for reuse.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain {
+            start: first_free_slot(&self.slots),
+            slots: &mut self.slots,
+            items: &mut self.items,
+            visited: 0,
+        }
+    }
+
+    // Removes and yields the entries `pred` accepts, lazily: entries not
+    // reached before the iterator is dropped stay in the map.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf {
+            start: first_free_slot(&self.slots),
+            slots: &mut self.slots,
+            items: &mut self.items,
+            visited: 0,
+            pred,
+        }
+    }
+
+    // Drops every entry but keeps the slots for reuse.
+    pub fn clear(&mut self) {
+        self.slots.fill_with(|| None);
+        self.items = 0;
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.slots.capacity() * mem::size_of::<Option<Slot<K, V>>>()
+    }
+
+    pub fn compact(&mut self) -> usize {
+        let before = self.allocated_bytes();
+
+        let target_size = self.min_buckets_for(self.items);
+        if target_size < self.slots.len() {
+            self.resize_to(target_size);
+        }
+        self.slots.shrink_to_fit();
+
+        before - self.allocated_bytes()
+    }
+
+    pub fn partition(self, n: usize) -> Vec<HashMap<K, V, S>>
+    where
+        S: Clone,
+    {
+        assert!(n > 0, "cannot partition a map into zero parts");
+
+        let mut parts: Vec<HashMap<K, V, S>> = (0..n)
+            .map(|_| {
+                let mut part = HashMap::with_hasher(self.hash_builder.clone());
+                part.growth = self.growth;
+                part.max_load = self.max_load;
+                part.min_load = self.min_load;
+                part
+            })
+            .collect();
+        for slot in self.slots.into_iter().flatten() {
+            // Every part shares the hasher, so the cached hash stays valid.
+            let part = &mut parts[part_for(slot.hash, n)];
+            part.reserve(1);
+            part.place_new(slot.hash, slot.key, slot.value);
+        }
+
+        parts
+    }
+
+    // Panics unless every entry caches its own hash, sits `distance` slots
+    // past its home, and follows Robin Hood order: walking forward, distance
+    // grows by at most one per slot and a run only starts at distance 0.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let n_slots = self.slots.len();
+        let mut items = 0;
+        for (idx, slot) in self.slots.iter().enumerate() {
+            let Some(slot) = slot else { continue };
+            items += 1;
+            assert_eq!(slot.hash, self.hash(&slot.key), "stale hash at {idx}");
+            let home = bucket_for(slot.hash, n_slots);
+            assert_eq!(
+                (home + slot.distance) % n_slots,
+                idx,
+                "wrong distance at {idx}"
+            );
+            let previous = &self.slots[(idx + n_slots - 1) % n_slots];
+            let max_distance = previous.as_ref().map_or(0, |prev| prev.distance + 1);
+            assert!(slot.distance <= max_distance, "out of order at {idx}");
+        }
+        assert_eq!(items, self.items, "item count is off");
+        assert!(n_slots == 0 || items < n_slots, "no free slot left");
     }
 }
 
-impl<K, V> Default for HashMap<K, V>
+// The table always has a free slot once it has any, and runs of occupied
+// slots never reach across one, so removing iterators that start there
+// never move an entry they have already seen.
+fn first_free_slot<K, V>(slots: &Slots<K, V>) -> usize {
+    slots.iter().position(Option::is_none).unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cursor(pub u64);
+
+impl<K, V, S> HashMap<K, V, S>
 where
-    K: Hash + Eq,
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
 {
-    fn default() -> Self {
-        Self::new()
+    // The cursor is a position in mixed-hash space. Every home slot owns one
+    // contiguous range of positions, in order, whatever the slot count, so
+    // walking home slots in order survives resizes between calls: entries
+    // that stay in the map for the whole scan are returned at least once.
+    // All entries of a home slot are returned together, so a page may exceed
+    // `limit`.
+    pub fn scan(&self, cursor: Cursor, limit: usize) -> (Vec<(K, V)>, Option<Cursor>) {
+        let mut page = Vec::new();
+        if self.slots.is_empty() {
+            return (page, None);
+        }
+
+        // Cursors come from callers, so clamp them to the range of hashes
+        // before scaling them down to a home.
+        let n_slots = self.slots.len() as WideHash;
+        let position = (cursor.0 as WideHash).min(HashValue::MAX as WideHash);
+        let mut home = ((position * n_slots) >> HashValue::BITS) as usize;
+        while home < self.slots.len() {
+            page.extend(
+                self.entries_at_home(home)
+                    .map(|slot| (slot.key.clone(), slot.value.clone())),
+            );
+
+            home += 1;
+            if page.len() >= limit && home < self.slots.len() {
+                // First position whose home is the next slot.
+                let position: WideHash = ((home as WideHash) << HashValue::BITS).div_ceil(n_slots);
+                return (page, Some(Cursor(position as u64)));
+            }
+        }
+        (page, None)
+    }
+
+    // Entries whose home is `home`. They sit together, after any entries
+    // from earlier homes that were pushed past `home`.
+    fn entries_at_home(&self, home: usize) -> impl Iterator<Item = &Slot<K, V>> {
+        let n_slots = self.slots.len();
+        (0..n_slots)
+            .map(move |distance| (distance, &self.slots[(home + distance) % n_slots]))
+            .map_while(|(distance, slot)| {
+                slot.as_ref()
+                    .filter(|slot| slot.distance >= distance)
+                    .map(|slot| (distance, slot))
+            })
+            .filter_map(|(distance, slot)| (slot.distance == distance).then_some(slot))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn iter(&self) -> HashIter<'_, K, V> {
+        HashIter::new(self)
+    }
 
-    #[test]
-    fn basic_functionality() {
-        let mut map = HashMap::new();
-        assert!(map.is_empty());
-        assert_eq!(map.len(), 0);
-        map.insert("foo", 42);
-        assert!(!map.is_empty());
-        assert_eq!(map.len(), 1);
-        assert_eq!(map.get("foo"), Some(&42));
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let offset = self.iter_offset.checked_rem(self.slots.len()).unwrap_or(0);
+        let (head, tail) = self.slots.split_at_mut(offset);
+        IterMut {
+            slots: tail.iter_mut().chain(head),
+            items: self.items,
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    pub fn into_values(self) -> IntoValues<K, V> {
+        IntoValues {
+            inner: self.into_iter(),
+        }
+    }
+}
+
+type SlotIter<'a, K, V> =
+    Chain<slice::Iter<'a, Option<Slot<K, V>>>, slice::Iter<'a, Option<Slot<K, V>>>>;
+type SlotIterMut<'a, K, V> =
+    Chain<slice::IterMut<'a, Option<Slot<K, V>>>, slice::IterMut<'a, Option<Slot<K, V>>>>;
+
+// Both borrowed iterators walk the slots from the map's iteration offset to
+// the end and then wrap around to the front.
+pub struct HashIter<'a, K, V> {
+    slots: SlotIter<'a, K, V>,
+    items: usize,
+}
+
+impl<'a, K, V> HashIter<'a, K, V> {
+    pub fn new<S>(hash_map: &'a HashMap<K, V, S>) -> Self {
+        let offset = hash_map
+            .iter_offset
+            .checked_rem(hash_map.slots.len())
+            .unwrap_or(0);
+        let (head, tail) = hash_map.slots.split_at(offset);
+        Self {
+            slots: tail.iter().chain(head),
+            items: hash_map.items,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for HashIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.slots.next()? {
+                self.items -= 1;
+                return Some((&slot.key, &slot.value));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<K, V> Clone for HashIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        HashIter {
+            slots: self.slots.clone(),
+            items: self.items,
+        }
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    slots: SlotIterMut<'a, K, V>,
+    items: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.slots.next()? {
+                self.items -= 1;
+                return Some((&slot.key, &mut slot.value));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = HashIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        HashIter::new(self)
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut HashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct IntoIter<K, V> {
+    slots: alloc::vec::IntoIter<Option<Slot<K, V>>>,
+    items: usize,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(slot) = self.slots.next()? {
+                self.items -= 1;
+                return Some((slot.key, slot.value));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<K, V, S> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        // Same order as borrowed iteration.
+        if let Some(offset) = self.iter_offset.checked_rem(self.slots.len()) {
+            self.slots.rotate_left(offset);
+        }
+        IntoIter {
+            slots: self.slots.into_iter(),
+            items: self.items,
+        }
+    }
+}
+
+// Drain walks backwards from a free slot, so the slot after each entry it
+// takes is already empty and nothing has to shift.
+pub struct Drain<'a, K, V> {
+    slots: &'a mut Slots<K, V>,
+    items: &'a mut usize,
+    start: usize,
+    visited: usize,
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n_slots = self.slots.len();
+        while self.visited < n_slots {
+            self.visited += 1;
+            let idx = (self.start + n_slots - self.visited) % n_slots;
+            if self.slots[idx].is_some() {
+                *self.items -= 1;
+                return Some(take_slot(self.slots, idx));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (*self.items, Some(*self.items))
+    }
+}
+
+impl<K, V> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        self.slots.fill_with(|| None);
+        *self.items = 0;
+    }
+}
+
+// ExtractIf walks forwards from a free slot. Taking an entry shifts only
+// the entries after it back by one, so it looks at the same slot again.
+pub struct ExtractIf<'a, K, V, F> {
+    slots: &'a mut Slots<K, V>,
+    items: &'a mut usize,
+    start: usize,
+    visited: usize,
+    pred: F,
+}
+
+impl<K, V, F> Iterator for ExtractIf<'_, K, V, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n_slots = self.slots.len();
+        while self.visited < n_slots {
+            let idx = (self.start + self.visited) % n_slots;
+            if let Some(slot) = &mut self.slots[idx] {
+                if (self.pred)(&slot.key, &mut slot.value) {
+                    *self.items -= 1;
+                    return Some(take_slot(self.slots, idx));
+                }
+            }
+            self.visited += 1;
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(*self.items))
+    }
+}
+
+impl<K, V> ExactSizeIterator for HashIter<'_, K, V> {}
+impl<K, V> ExactSizeIterator for Drain<'_, K, V> {}
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+// Iterators that yield one half of each entry of an underlying iterator.
+macro_rules! projection_iter {
+    ($name:ident $(<$lt:lifetime>)?, $inner:ident, $item:ty, |$entry:pat_param| $out:expr) => {
+        pub struct $name<$($lt,)? K, V> {
+            inner: $inner<$($lt,)? K, V>,
+        }
+
+        impl<$($lt,)? K, V> Iterator for $name<$($lt,)? K, V> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.inner.next().map(|$entry| $out)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<$($lt,)? K, V> ExactSizeIterator for $name<$($lt,)? K, V> {}
+    };
+}
+
+projection_iter!(Keys<'a>, HashIter, &'a K, |(key, _)| key);
+projection_iter!(Values<'a>, HashIter, &'a V, |(_, value)| value);
+projection_iter!(ValuesMut<'a>, IterMut, &'a mut V, |(_, value)| value);
+projection_iter!(IntoKeys, IntoIter, K, |(key, _)| key);
+projection_iter!(IntoValues, IntoIter, V, |(_, value)| value);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError<K> {
+    pub key: K,
+    // Position of the duplicate within the input.
+    pub index: usize,
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {:?} at index {}", self.key, self.index)
+    }
+}
+
+impl<K: fmt::Debug> Error for DuplicateKeyError<K> {}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, DuplicateKeyError<K>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = HashMap::with_hasher(S::default());
+        for (index, (key, value)) in iter.into_iter().enumerate() {
+            // The map is thrown away on error, so the stored key, equal to
+            // the duplicate, can be taken back out of it.
+            if let Err(err) = map.try_insert(key, value) {
+                let (key, _) = err.entry.remove_entry();
+                return Err(DuplicateKeyError { key, index });
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<K, V, S> fmt::Debug for HashMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Maps are equal when they hold the same entries, however those happen to be
+// laid out in their slots.
+impl<K, V, S> PartialEq for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K, V, S> Eq for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, Q, V, S> Index<&Q> for HashMap<K, V, S>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: ?Sized + Eq + Hash,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in HashMap")
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = HashMap::with_hasher(S::default());
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Some of the keys may already be present in a non-empty map, so
+        // only reserve for half of them there, as std does.
+        let (lower, _) = iter.size_hint();
+        let additional = if self.is_empty() {
+            lower
+        } else {
+            lower.div_ceil(2)
+        };
+        self.reserve(additional);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, S> Extend<(&'a K, &'a V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().map(|(&key, &value)| (key, value)));
+    }
+}
+
+// Only for the default hasher, like std's `From<[(K, V); N]>`, so that
+// `HashMap::try_from(entries)` needs no annotations.
+impl<K, V> TryFrom<Vec<(K, V)>> for HashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    type Error = DuplicateKeyError<K>;
+
+    fn try_from(entries: Vec<(K, V)>) -> Result<Self, Self::Error> {
+        Self::try_from_iter(entries)
+    }
+}
+
+impl<K, V, S> Default for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Counted, IdentityState};
+    use core::cell::Cell;
+    use core::hash::BuildHasherDefault;
+    use std::format;
+    use std::string::{String, ToString};
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = HashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+        map.insert("foo", 42);
+        assert!(!map.is_empty());
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("foo"), Some(&42));
         assert_eq!(map.remove("foo"), Some(42));
         assert!(map.is_empty());
         assert_eq!(map.len(), 0);
         assert_eq!(map.get("foo"), None);
     }
+
+    #[test]
+    fn lookups_on_empty_map() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        assert_eq!(map.get("foo"), None);
+        assert!(!map.contains_key("foo"));
+        assert_eq!(map.remove("foo"), None);
+    }
+
+    #[test]
+    fn insert_mut_returns_stored_value() {
+        let mut map = HashMap::new();
+        map.insert_mut("words", Vec::new()).push("first");
+        map.insert_mut("words", vec!["replaced"]).push("second");
+        assert_eq!(map.get("words"), Some(&vec!["replaced", "second"]));
+        assert_eq!(map.insert("words", Vec::new()).map(|v| v.len()), Some(2));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn inserts_that_refuse_or_skip_duplicates() {
+        let mut map = HashMap::new();
+        *map.try_insert("a", 1).unwrap() += 10;
+        let err = map.try_insert("a", 2).unwrap_err();
+        assert_eq!((*err.entry.get(), err.value), (11, 2));
+        assert_eq!(
+            err.to_string(),
+            "failed to insert 2, key \"a\" already exists with value 11"
+        );
+
+        assert_eq!(map.get("a"), Some(&11));
+
+        let mut loaded = HashMap::new();
+        for i in 0..100 {
+            *loaded.insert_unique_unchecked(i, i) += 1;
+        }
+        assert_eq!(loaded.len(), 100);
+        assert!((0..100).all(|i| loaded.get(&i) == Some(&(i + 1))));
+    }
+
+    #[test]
+    fn get_many_matches_get() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i * 3);
+        }
+        assert_eq!(
+            map.get_many([&1, &50, &100, &1]),
+            [Some(&3), Some(&150), None, Some(&3)]
+        );
+        assert_eq!(HashMap::<i32, i32>::new().get_many([&1]), [None]);
+    }
+
+    #[test]
+    fn get_many_mut_borrows_disjoint_entries() {
+        let mut balances = HashMap::new();
+        balances.insert("alice", 100);
+        balances.insert("bob", 20);
+
+        if let [Some(from), Some(to), None] = balances.get_many_mut(["alice", "bob", "carol"]) {
+            *from -= 30;
+            *to += 30;
+        }
+        assert_eq!(balances.get("alice"), Some(&70));
+        assert_eq!(balances.get("bob"), Some(&50));
+        assert_eq!(balances.get_many_mut(["carol", "carol"]), [None, None]);
+    }
+
+    #[test]
+    #[should_panic(expected = "same key twice")]
+    fn get_many_mut_rejects_repeated_keys() {
+        let mut map = HashMap::new();
+        map.insert("bob", 20);
+        map.get_many_mut(["bob", "bob"]);
+    }
+
+    #[test]
+    fn weak_hashes_spread_across_buckets() {
+        // Plain `hash % 64` would send every one of these to bucket 0.
+        let buckets: std::collections::HashSet<_> = (0..64)
+            .map(|id: HashValue| bucket_for(id << 16, 64))
+            .collect();
+        assert!(buckets.len() > 32, "only {} buckets used", buckets.len());
+        assert_eq!(bucket_for(HashValue::MAX, 1), 0);
+    }
+
+    #[test]
+    fn remove_many_reports_each_key() {
+        let mut map = HashMap::new();
+        for i in 0..10 {
+            map.insert(i.to_string(), i);
+        }
+        let removed = map.remove_many(["3", "missing", "7", "3"]);
+        assert_eq!(removed, [Some(3), None, Some(7), None]);
+        assert_eq!(map.len(), 8);
+        assert!(!map.contains_key("7"));
+    }
+
+    #[test]
+    fn shuffled_iteration_visits_every_entry() {
+        let orders: std::collections::HashSet<Vec<i32>> = (0..20)
+            .map(|_| {
+                let mut map = HashMap::new().shuffled_iteration(true);
+                for i in 0..16 {
+                    map.insert(i, i);
+                }
+                let keys: Vec<i32> = (&map).into_iter().map(|(&key, _)| key).collect();
+                let mut sorted = keys.clone();
+                sorted.sort();
+                assert_eq!(sorted, (0..16).collect::<Vec<_>>());
+                keys
+            })
+            .collect();
+        // Without std every map gets the same fixed seed.
+        #[cfg(feature = "std")]
+        assert!(orders.len() > 1);
+        #[cfg(not(feature = "std"))]
+        assert_eq!(orders.len(), 1);
+    }
+
+    #[test]
+    fn custom_hashers_are_deterministic() {
+        let build = || {
+            let mut map = HashMap::with_capacity_and_hasher(16, fnv::FnvBuildHasher::default())
+                .shuffled_iteration(false);
+            for i in 0..16 {
+                map.insert(i, i);
+            }
+            (&map).into_iter().map(|(&key, _)| key).collect::<Vec<_>>()
+        };
+        assert_eq!(build(), build());
+
+        let map: HashMap<i32, i32, fnv::FnvBuildHasher> = HashMap::default();
+        assert_eq!(
+            map.hasher().hash_one(1),
+            fnv::FnvBuildHasher::default().hash_one(1)
+        );
+    }
+
+    #[test]
+    fn owned_keys_come_back_from_borrowed_lookups() {
+        let mut map = HashMap::new();
+        map.insert("key".to_string(), 1);
+
+        let (key, value) = map.get_key_value("key").unwrap();
+        assert_eq!((key.as_str(), *value), ("key", 1));
+        assert_eq!(map.get_key_value("other"), None);
+        assert_eq!(map.remove_entry("key"), Some(("key".to_string(), 1)));
+        assert_eq!(map.remove_entry("key"), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn get_mut_updates_in_place() {
+        let mut map = HashMap::new();
+        map.insert("count", 1);
+        *map.get_mut("count").unwrap() += 1;
+        assert_eq!(map.get("count"), Some(&2));
+        assert_eq!(map.get_mut("missing"), None);
+        assert_eq!(HashMap::<&str, i32>::new().get_mut("count"), None);
+    }
+
+    #[test]
+    fn owned_iteration_moves_entries_out() {
+        let mut map = HashMap::new();
+        for i in 0..50 {
+            map.insert(i, i.to_string());
+        }
+        let borrowed: Vec<i32> = (&map).into_iter().map(|(&key, _)| key).collect();
+
+        let iter = map.into_iter();
+        assert_eq!(iter.size_hint(), (50, Some(50)));
+        let owned: Vec<(i32, String)> = iter.collect();
+        assert_eq!(
+            owned.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            borrowed
+        );
+        assert!(owned.iter().all(|(key, value)| *value == key.to_string()));
+    }
+
+    #[test]
+    fn iter_mut_updates_every_value() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        for (key, value) in map.iter_mut() {
+            *value += key;
+        }
+        for (_, value) in &mut map {
+            *value += 1;
+        }
+
+        assert_eq!(map.iter().count(), 100);
+        assert!(map.iter().all(|(key, value)| *value == 2 * key + 1));
+    }
+
+    #[test]
+    fn projection_iterators() {
+        let mut map = HashMap::new();
+        for i in 0..10 {
+            map.insert(i, i * 10);
+        }
+        for value in map.values_mut() {
+            *value += 1;
+        }
+
+        assert_eq!(map.keys().len(), 10);
+        assert_eq!(map.keys().zip(map.values()).count(), 10);
+        assert!(map.iter().zip(map.keys()).all(|((a, _), b)| a == b));
+        assert_eq!(map.values().sum::<i32>(), 460);
+        let values: Vec<i32> = map.values().copied().collect();
+        let mut keys: Vec<i32> = map.into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+
+        let mut map = HashMap::new();
+        map.insert("key", values);
+        assert_eq!(
+            map.into_values().next().map(|values| values.len()),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut map: HashMap<_, _> = (0..100).map(|i| (i, i * i)).collect();
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.slots.len(), 256);
+
+        let other: HashMap<_, _> = (100..110).map(|i| (i, 0)).collect();
+        map.extend(&other);
+        map.extend([(0, -1)]);
+        assert_eq!(map.len(), 110);
+        assert_eq!(map.get(&0), Some(&-1));
+        assert_eq!(map.get(&105), Some(&0));
+    }
+
+    #[test]
+    fn capacity_management() {
+        let mut map = HashMap::with_capacity(100_000);
+        let buckets = map.slots.len();
+        assert!(map.capacity() >= 100_000);
+        for i in 0..100_000 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.slots.len(), buckets);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.capacity(), 3 * buckets / 4 + 1);
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 0);
+
+        for _ in 0..10 {
+            map.reserve(1);
+            let capacity = map.capacity();
+            map.insert(map.len(), 0);
+            assert_eq!(map.capacity(), capacity);
+        }
+        assert!(map.slots.len() < 32);
+    }
+
+    #[test]
+    fn bulk_removal() {
+        let mut map: HashMap<_, _> = (0..100).map(|i| (i, i)).collect();
+        map.retain(|key, value| {
+            *value += 1;
+            key % 2 == 0
+        });
+        assert_eq!(map.len(), 50);
+        assert_eq!(map.get(&10), Some(&11));
+
+        let mut extracted: Vec<_> = map.extract_if(|key, _| key % 10 == 0).collect();
+        extracted.sort();
+        assert_eq!(
+            extracted,
+            (0..10).map(|i| (i * 10, i * 10 + 1)).collect::<Vec<_>>()
+        );
+        assert_eq!(map.len(), 40);
+        assert_eq!(map.iter().count(), 40);
+
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 40);
+        drain.next();
+        drop(drain);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+    }
+
+    #[test]
+    fn standard_traits() {
+        let map: HashMap<_, _> = [("a", 1)].into_iter().collect();
+        assert_eq!(format!("{map:?}"), r#"{"a": 1}"#);
+        assert_eq!(map["a"], 1);
+
+        // Same entries, different bucket counts.
+        let mut copy = map.clone();
+        copy.reserve(100);
+        assert_eq!(copy, map);
+        copy.insert("b", 2);
+        assert_ne!(copy, map);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn reserve_panics_instead_of_wrapping() {
+        let mut map = HashMap::new();
+        map.insert(1, 1);
+        map.reserve(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn with_capacity_panics_instead_of_wrapping() {
+        HashMap::<u8, u8>::with_capacity(usize::MAX / 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn index_panics_on_missing_key() {
+        let map: HashMap<&str, i32> = HashMap::new();
+        let _ = map["missing"];
+    }
+
+    #[test]
+    fn partition_into_disjoint_maps() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i * 2);
+        }
+
+        let parts = map.partition(4);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts.iter().map(HashMap::len).sum::<usize>(), 100);
+        for i in 0..100 {
+            let owners: Vec<_> = parts.iter().filter_map(|part| part.get(&i)).collect();
+            assert_eq!(owners, [&(i * 2)]);
+        }
+    }
+
+    #[test]
+    fn load_factors_bound_the_table_size() {
+        let mut map = HashMap::new();
+        for i in 0..10_000 {
+            map.insert(i, i);
+        }
+        let peak = map.slots.len();
+        for i in 100..10_000 {
+            map.remove(&i);
+        }
+        assert!(map.slots.len() * 8 <= peak, "{} slots", map.slots.len());
+        assert!((0..100).all(|i| map.get(&i) == Some(&i)));
+
+        let mut dense = HashMap::new().max_load_factor(0.9).shrink_below(0.0);
+        for i in 0..900 {
+            dense.insert(i, i);
+        }
+        assert_eq!(dense.slots.len(), 1024);
+        assert_eq!(dense.capacity(), 922);
+        dense.retain(|_, _| false);
+        assert_eq!(dense.slots.len(), 1024);
+    }
+
+    #[test]
+    fn compact_reclaims_memory_after_churn() {
+        let mut map = HashMap::new();
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+        for i in 10..1000 {
+            map.remove(&i);
+        }
+
+        assert!(map.compact() > 0);
+        assert_eq!(map.compact(), 0);
+        assert_eq!(map.len(), 10);
+        for i in 0..10 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        map.insert(10, 10);
+        assert_eq!(map.get(&10), Some(&10));
+    }
+
+    #[test]
+    fn reserve_exact_avoids_resizes() {
+        let mut map = HashMap::new();
+        map.insert(0, 0);
+        map.reserve_exact(99);
+        let n_buckets = map.slots.len();
+        assert_eq!(n_buckets, 256);
+        for i in 1..100 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.slots.len(), n_buckets);
+
+        map.reserve_exact(0);
+        assert_eq!(map.slots.len(), n_buckets);
+    }
+
+    #[test]
+    fn try_from_rejects_duplicates() {
+        let map = HashMap::try_from(vec![("foo", 1), ("bar", 2)]).unwrap();
+        assert_eq!(map.len(), 2);
+
+        let err = HashMap::try_from(vec![("foo", 1), ("bar", 2), ("foo", 3)])
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            DuplicateKeyError {
+                key: "foo",
+                index: 2
+            }
+        );
+        assert_eq!(err.to_string(), "duplicate key \"foo\" at index 2");
+    }
+
+    #[test]
+    fn scan_survives_resizes() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        let mut cursor = Some(Cursor::default());
+        let mut next_key = 100;
+        while let Some(position) = cursor {
+            let (page, next) = map.scan(position, 10);
+            seen.extend(page.into_iter().map(|(key, _)| key));
+            cursor = next;
+            for _ in 0..50 {
+                map.insert(next_key, next_key);
+                next_key += 1;
+            }
+        }
+        assert!((0..100).all(|key| seen.contains(&key)));
+    }
+
+    #[test]
+    fn scan_ends_on_out_of_range_cursors() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        let (_, next) = map.scan(Cursor(u64::MAX), 10);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn repeated_extends_grow_geometrically() {
+        let mut map = HashMap::new().with_growth(Growth {
+            factor: GrowthFactor::OneAndHalf,
+            prime_sizes: true,
+        });
+        let mut resizes = 0;
+        for i in 0..1000 {
+            let before = map.slots.len();
+            map.extend([(i, i)]);
+            resizes += usize::from(map.slots.len() != before);
+        }
+        assert!(resizes < 20, "{resizes} resizes");
+    }
+
+    #[test]
+    fn growth_policies() {
+        let grown = |growth: Growth| {
+            let mut map = HashMap::new().with_growth(growth);
+            for i in 0..1000 {
+                map.insert(i, i);
+            }
+            assert!((0..1000).all(|i| map.get(&i) == Some(&i)));
+            let (page, _) = map.scan(Cursor::default(), usize::MAX);
+            assert_eq!(page.len(), 1000);
+            map.slots.len()
+        };
+
+        assert_eq!(grown(Growth::default()), 2048);
+        assert_eq!(
+            grown(Growth {
+                factor: GrowthFactor::OneAndHalf,
+                prime_sizes: false,
+            }),
+            1598
+        );
+        let primes = grown(Growth {
+            factor: GrowthFactor::Double,
+            prime_sizes: true,
+        });
+        assert_eq!(next_prime(primes), primes);
+    }
+
+    #[test]
+    fn partition_spreads_weak_hashes() {
+        let mut map = HashMap::with_hasher(IdentityState::default());
+        for i in 0..10_000u64 {
+            map.insert(i, i);
+        }
+
+        let parts = map.partition(4);
+        assert!(parts.iter().all(|part| part.len() > 2000));
+    }
+
+    #[test]
+    fn keys_are_hashed_once() {
+        let hashes = Cell::new(0);
+        let mut map = HashMap::new();
+        for id in 0..1000 {
+            map.insert(Counted::new(id, &hashes), id);
+        }
+        assert_eq!(hashes.get(), 1000);
+
+        let parts = map.partition(4);
+        assert_eq!(parts.iter().map(HashMap::len).sum::<usize>(), 1000);
+        assert_eq!(hashes.get(), 1000);
+    }
+
+    #[test]
+    fn long_probe_runs_survive_removals() {
+        // Sends every key to one of four home slots, so the whole table is
+        // a few long runs that removals have to shift back.
+        #[derive(Default)]
+        struct FourHomes(u64);
+
+        impl std::hash::Hasher for FourHomes {
+            fn finish(&self) -> u64 {
+                self.0 % 4
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for &byte in bytes {
+                    self.0 = self.0.wrapping_mul(31).wrapping_add(byte as u64);
+                }
+            }
+        }
+
+        let mut map = HashMap::with_hasher(BuildHasherDefault::<FourHomes>::default());
+        for i in 0..200 {
+            map.insert(i, i);
+        }
+        map.check_invariants();
+        for i in (0..200).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        map.check_invariants();
+
+        assert_eq!(map.len(), 100);
+        assert!((0..200).all(|i| map.contains_key(&i) == (i % 2 == 1)));
+        let (page, next) = map.scan(Cursor::default(), usize::MAX);
+        assert_eq!((page.len(), next), (100, None));
+        assert_eq!(map.extract_if(|&key, _| key < 100).count(), 50);
+        assert!((100..200).all(|i| map.contains_key(&i) == (i % 2 == 1)));
+        assert_eq!(map.drain().count(), 50);
+        assert!(map.is_empty());
+    }
 }
@@ -0,0 +1,182 @@
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::concurrent::ConcurrentHashMap;
+
+struct KeySlot {
+    locked: Mutex<bool>,
+    released: Condvar,
+}
+
+// Hands out one guard per key at a time. A key's slot only exists while some
+// thread holds or waits for it: the last guard to go removes it again. Slots
+// are sharded, so only keys in the same shard contend to find their slot.
+pub struct LockMap<K> {
+    slots: ConcurrentHashMap<K, Arc<KeySlot>>,
+}
+
+pub struct KeyGuard<'a, K>
+where
+    K: Hash + Eq,
+{
+    map: &'a LockMap<K>,
+    key: K,
+    slot: Arc<KeySlot>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // The protected state is updated in single steps, so a panic elsewhere
+    // cannot leave it inconsistent.
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K> LockMap<K>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new() -> Self {
+        LockMap {
+            slots: ConcurrentHashMap::new(),
+        }
+    }
+
+    fn slot(&self, key: &K) -> Arc<KeySlot> {
+        let mut slots = self.slots.write_shard(key);
+        if let Some(slot) = slots.get(key) {
+            return slot.clone();
+        }
+        let slot = Arc::new(KeySlot {
+            locked: Mutex::new(false),
+            released: Condvar::new(),
+        });
+        slots.insert(key.clone(), slot.clone());
+        slot
+    }
+
+    pub fn lock(&self, key: &K) -> KeyGuard<'_, K> {
+        let slot = self.slot(key);
+        {
+            let mut locked = lock(&slot.locked);
+            while *locked {
+                locked = slot
+                    .released
+                    .wait(locked)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            *locked = true;
+        }
+        KeyGuard {
+            map: self,
+            key: key.clone(),
+            slot,
+        }
+    }
+
+    pub fn try_lock(&self, key: &K) -> Option<KeyGuard<'_, K>> {
+        let slot = self.slot(key);
+        if std::mem::replace(&mut *lock(&slot.locked), true) {
+            self.release_slot(key, &slot);
+            return None;
+        }
+        Some(KeyGuard {
+            map: self,
+            key: key.clone(),
+            slot,
+        })
+    }
+
+    // Number of keys currently locked or waited on.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl<K> LockMap<K>
+where
+    K: Hash + Eq,
+{
+    fn release_slot(&self, key: &K, slot: &Arc<KeySlot>) {
+        let mut slots = self.slots.write_shard(key);
+        // Clones are only handed out under the key's shard lock, so two
+        // references (the map's and the caller's) means nobody else holds or
+        // waits for `key`.
+        if Arc::strong_count(slot) == 2 {
+            slots.remove(key);
+        }
+    }
+}
+
+impl<K> Default for LockMap<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> KeyGuard<'_, K>
+where
+    K: Hash + Eq,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K> Drop for KeyGuard<'_, K>
+where
+    K: Hash + Eq,
+{
+    fn drop(&mut self) {
+        *lock(&self.slot.locked) = false;
+        self.slot.released.notify_one();
+        self.map.release_slot(&self.key, &self.slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn serializes_work_per_key() {
+        let locks = LockMap::new();
+        let in_flight = AtomicUsize::new(0);
+        let overlaps = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..50 {
+                        let _guard = locks.lock(&"resource");
+                        if in_flight.fetch_add(1, Ordering::SeqCst) != 0 {
+                            overlaps.fetch_add(1, Ordering::SeqCst);
+                        }
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(overlaps.load(Ordering::SeqCst), 0);
+        assert!(locks.is_empty());
+    }
+
+    #[test]
+    fn try_lock_fails_while_held() {
+        let locks = LockMap::new();
+        let guard = locks.lock(&1);
+        assert!(locks.try_lock(&1).is_none());
+        assert!(locks.try_lock(&2).is_some());
+        assert_eq!(locks.len(), 1);
+        drop(guard);
+        assert!(locks.is_empty());
+    }
+}
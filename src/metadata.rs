@@ -0,0 +1,138 @@
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::hash::Hash;
+use std::time::Instant;
+
+use crate::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub inserted_at: Instant,
+    pub last_access: Instant,
+    pub access_count: u64,
+}
+
+// Reads only take `&self`, so the access fields live in cells.
+struct Tracking {
+    inserted_at: Instant,
+    last_access: Cell<Instant>,
+    access_count: Cell<u64>,
+}
+
+impl Tracking {
+    fn new() -> Self {
+        let now = Instant::now();
+        Tracking {
+            inserted_at: now,
+            last_access: Cell::new(now),
+            access_count: Cell::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        self.last_access.set(Instant::now());
+        self.access_count.set(self.access_count.get() + 1);
+    }
+}
+
+pub struct TrackedHashMap<K, V> {
+    map: HashMap<K, (V, Tracking)>,
+}
+
+impl<K, V> TrackedHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        TrackedHashMap {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map
+            .insert(key, (value, Tracking::new()))
+            .map(|(old_value, _)| old_value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (value, tracking) = self.map.get(key)?;
+        tracking.touch();
+        Some(value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.remove(key).map(|(value, _)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn metadata<Q>(&self, key: &Q) -> Option<EntryMetadata>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (_, tracking) = self.map.get(key)?;
+        Some(EntryMetadata {
+            inserted_at: tracking.inserted_at,
+            last_access: tracking.last_access.get(),
+            access_count: tracking.access_count.get(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V> Default for TrackedHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_update_metadata() {
+        let mut map = TrackedHashMap::new();
+        map.insert("foo", 42);
+        let inserted = map.metadata("foo").unwrap();
+        assert_eq!(inserted.access_count, 0);
+        assert_eq!(inserted.last_access, inserted.inserted_at);
+
+        assert_eq!(map.get("foo"), Some(&42));
+        assert_eq!(map.get("foo"), Some(&42));
+        let read = map.metadata("foo").unwrap();
+        assert_eq!(read.access_count, 2);
+        assert_eq!(read.inserted_at, inserted.inserted_at);
+        assert!(read.last_access >= inserted.last_access);
+
+        map.insert("foo", 43);
+        assert_eq!(map.metadata("foo").unwrap().access_count, 0);
+        assert_eq!(map.metadata("bar"), None);
+    }
+}
@@ -0,0 +1,91 @@
+use alloc::borrow::ToOwned;
+use core::hash::Hash;
+
+use crate::HashMap;
+
+// Runs every key through `normalize` before it reaches the table, on inserts
+// and lookups alike, so inputs that only differ in spelling ("Foo ", "foo")
+// share one entry. Stored keys are the normalized form.
+pub struct NormalizedMap<K, V, F> {
+    map: HashMap<K, V>,
+    normalize: F,
+}
+
+impl<K, V, F> NormalizedMap<K, V, F>
+where
+    K: Hash + Eq,
+    F: Fn(K) -> K,
+{
+    pub fn new(normalize: F) -> Self {
+        NormalizedMap {
+            map: HashMap::new(),
+            normalize,
+        }
+    }
+
+    fn normalized<Q>(&self, key: &Q) -> K
+    where
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        (self.normalize)(key.to_owned())
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let key = (self.normalize)(key);
+        self.map.insert(key, value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.map.get(&self.normalized(key))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.map.contains_key(&self.normalized(key))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        let key = self.normalized(key);
+        self.map.remove(&key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::{String, ToString};
+
+    #[test]
+    fn equivalent_spellings_share_an_entry() {
+        let mut map = NormalizedMap::new(|key: String| key.trim().to_lowercase());
+        assert_eq!(map.insert("  Alice".to_string(), 1), None);
+        assert_eq!(map.insert("ALICE ".to_string(), 2), Some(1));
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("alice"), Some(&2));
+        assert!(map.contains_key(" aLiCe "));
+        assert!(map.iter().all(|(key, _)| key == "alice"));
+        assert_eq!(map.remove("Alice"), Some(2));
+        assert!(map.is_empty());
+    }
+}
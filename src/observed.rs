@@ -0,0 +1,130 @@
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+use crate::entry::Entry;
+use crate::HashMap;
+
+pub trait Observer<K, V> {
+    fn on_insert(&mut self, _key: &K, _value: &V) {}
+
+    fn on_update(&mut self, _key: &K, _old_value: &V, _new_value: &V) {}
+
+    fn on_remove(&mut self, _key: &K, _value: &V) {}
+}
+
+pub struct ObservedHashMap<K, V, O> {
+    map: HashMap<K, V>,
+    observer: O,
+}
+
+impl<K, V, O> ObservedHashMap<K, V, O>
+where
+    K: Hash + Eq,
+    O: Observer<K, V>,
+{
+    pub fn new(observer: O) -> Self {
+        ObservedHashMap {
+            map: HashMap::new(),
+            observer,
+        }
+    }
+
+    // Goes through the entry API rather than `HashMap::insert`, whose result
+    // comes back after the key has been moved into the map, so that one
+    // lookup both picks the event and keeps the key at hand to report it.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.map.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old_value = entry.insert(value);
+                self.observer
+                    .on_update(entry.key(), &old_value, entry.get());
+                Some(old_value)
+            }
+            Entry::Vacant(entry) => {
+                self.observer.on_insert(entry.key(), &value);
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (key, value) = self.map.remove_entry(key)?;
+        self.observer.on_remove(&key, &value);
+        Some(value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::format;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[derive(Default)]
+    struct EventLog(Vec<String>);
+
+    impl Observer<&'static str, i32> for EventLog {
+        fn on_insert(&mut self, key: &&'static str, value: &i32) {
+            self.0.push(format!("insert {key} {value}"));
+        }
+
+        fn on_update(&mut self, key: &&'static str, old_value: &i32, new_value: &i32) {
+            self.0.push(format!("update {key} {old_value} {new_value}"));
+        }
+
+        fn on_remove(&mut self, key: &&'static str, value: &i32) {
+            self.0.push(format!("remove {key} {value}"));
+        }
+    }
+
+    #[test]
+    fn observer_sees_mutations() {
+        let mut map = ObservedHashMap::new(EventLog::default());
+        map.insert("foo", 1);
+        map.insert("foo", 2);
+        assert_eq!(map.remove("foo"), Some(2));
+        assert_eq!(map.remove(&"foo"), None);
+        assert_eq!(
+            map.observer().0,
+            ["insert foo 1", "update foo 1 2", "remove foo 2"]
+        );
+    }
+}
@@ -0,0 +1,447 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::slice;
+
+use crate::{hash_one, DefaultState, HashValue};
+
+const BITS_PER_LEVEL: u32 = 5;
+const LEVEL_MASK: HashValue = (1 << BITS_PER_LEVEL) - 1;
+
+// Hash array mapped trie. Each branch level consumes the next five bits of
+// the hash, and only the children that exist are stored, packed in bitmap
+// order. A leaf holds the full hash and every entry that has it, so it can
+// sit at any depth and collisions need no special node.
+enum Node<K, V> {
+    Branch {
+        bitmap: u32,
+        children: Vec<Arc<Node<K, V>>>,
+    },
+    Leaf {
+        hash: HashValue,
+        entries: Vec<(K, V)>,
+    },
+}
+
+// Bit of `bitmap` for `hash` at the level starting at `shift`. Branches only
+// exist where two different hashes agree on every lower bit, so `shift` is
+// always below the hash width here.
+fn level_bit(hash: HashValue, shift: u32) -> u32 {
+    1 << ((hash >> shift) & LEVEL_MASK)
+}
+
+fn child_pos(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<K, V> Node<K, V> {
+    fn leaf(hash: HashValue, key: K, value: V) -> Arc<Self> {
+        Arc::new(Node::Leaf {
+            hash,
+            entries: vec![(key, value)],
+        })
+    }
+
+    fn get<'a, Q>(mut node: &'a Self, hash: HashValue, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        let mut shift = 0;
+        loop {
+            match node {
+                Node::Branch { bitmap, children } => {
+                    let bit = level_bit(hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    node = &children[child_pos(*bitmap, bit)];
+                    shift += BITS_PER_LEVEL;
+                }
+                Node::Leaf {
+                    hash: leaf_hash,
+                    entries,
+                } => {
+                    if *leaf_hash != hash {
+                        return None;
+                    }
+                    let (_, value) = entries.iter().find(|(k, _)| k.borrow() == key)?;
+                    return Some(value);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> Node<K, V>
+where
+    K: Eq + Clone,
+    V: Clone,
+{
+    // Copies the path down to the changed leaf and shares everything else.
+    // Also reports whether the key was new.
+    fn insert(
+        node: &Arc<Self>,
+        hash: HashValue,
+        shift: u32,
+        key: K,
+        value: V,
+    ) -> (Arc<Self>, bool) {
+        match &**node {
+            Node::Branch { bitmap, children } => {
+                let bit = level_bit(hash, shift);
+                let pos = child_pos(*bitmap, bit);
+                let mut children = children.clone();
+                let added = if bitmap & bit == 0 {
+                    children.insert(pos, Node::leaf(hash, key, value));
+                    true
+                } else {
+                    let (child, added) =
+                        Node::insert(&children[pos], hash, shift + BITS_PER_LEVEL, key, value);
+                    children[pos] = child;
+                    added
+                };
+                let branch = Node::Branch {
+                    bitmap: bitmap | bit,
+                    children,
+                };
+                (Arc::new(branch), added)
+            }
+            Node::Leaf {
+                hash: leaf_hash,
+                entries,
+            } if *leaf_hash == hash => {
+                let mut entries = entries.clone();
+                let added = match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some(entry) => {
+                        entry.1 = value;
+                        false
+                    }
+                    None => {
+                        entries.push((key, value));
+                        true
+                    }
+                };
+                (Arc::new(Node::Leaf { hash, entries }), added)
+            }
+            Node::Leaf {
+                hash: leaf_hash, ..
+            } => {
+                // Push the existing leaf one level down and retry from there.
+                let branch = Arc::new(Node::Branch {
+                    bitmap: level_bit(*leaf_hash, shift),
+                    children: vec![node.clone()],
+                });
+                Node::insert(&branch, hash, shift, key, value)
+            }
+        }
+    }
+
+    // `None` if the key is absent; otherwise the replacement node, which is
+    // itself `None` once the subtree is empty.
+    fn remove<Q>(
+        node: &Arc<Self>,
+        hash: HashValue,
+        shift: u32,
+        key: &Q,
+    ) -> Option<Option<Arc<Self>>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        match &**node {
+            Node::Branch { bitmap, children } => {
+                let bit = level_bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let pos = child_pos(*bitmap, bit);
+                let child = Node::remove(&children[pos], hash, shift + BITS_PER_LEVEL, key)?;
+
+                let mut children = children.clone();
+                let mut bitmap = *bitmap;
+                match child {
+                    Some(child) => children[pos] = child,
+                    None => {
+                        children.remove(pos);
+                        bitmap &= !bit;
+                    }
+                }
+                // A lone leaf can stand in for its branch, which keeps
+                // paths short after removals.
+                match children.as_slice() {
+                    [] => Some(None),
+                    [only] if matches!(**only, Node::Leaf { .. }) => Some(Some(only.clone())),
+                    _ => Some(Some(Arc::new(Node::Branch { bitmap, children }))),
+                }
+            }
+            Node::Leaf {
+                hash: leaf_hash,
+                entries,
+            } => {
+                if *leaf_hash != hash {
+                    return None;
+                }
+                let pos = entries.iter().position(|(k, _)| k.borrow() == key)?;
+                if entries.len() == 1 {
+                    return Some(None);
+                }
+                let mut entries = entries.clone();
+                entries.swap_remove(pos);
+                Some(Some(Arc::new(Node::Leaf { hash, entries })))
+            }
+        }
+    }
+}
+
+// Immutable map whose updates return a new map. The new map shares every
+// node off the changed path with the old one, so cloning is O(1) and an
+// update copies O(log n) nodes.
+pub struct PersistentHashMap<K, V, S = DefaultState> {
+    root: Option<Arc<Node<K, V>>>,
+    len: usize,
+    hash_builder: S,
+}
+
+impl<K, V> PersistentHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultState::default())
+    }
+}
+
+impl<K, V, S> PersistentHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PersistentHashMap {
+            root: None,
+            len: 0,
+            hash_builder,
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        Node::get(self.root.as_ref()?, hash_one(&self.hash_builder, key), key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, V, S> PersistentHashMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let hash = hash_one(&self.hash_builder, &key);
+        let (root, added) = match &self.root {
+            Some(root) => Node::insert(root, hash, 0, key, value),
+            None => (Node::leaf(hash, key, value), true),
+        };
+        PersistentHashMap {
+            root: Some(root),
+            len: self.len + added as usize,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let hash = hash_one(&self.hash_builder, key);
+        let removed = self
+            .root
+            .as_ref()
+            .and_then(|root| Node::remove(root, hash, 0, key));
+        match removed {
+            Some(root) => PersistentHashMap {
+                root,
+                len: self.len - 1,
+                hash_builder: self.hash_builder.clone(),
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl<K, V, S> PersistentHashMap<K, V, S> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            pending: self.root.iter().map(|root| &**root).collect(),
+            current: [].iter(),
+            items: self.len,
+        }
+    }
+}
+
+impl<K, V, S: Clone> Clone for PersistentHashMap<K, V, S> {
+    fn clone(&self) -> Self {
+        PersistentHashMap {
+            root: self.root.clone(),
+            len: self.len,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+}
+
+// Depth-first over the trie, with `pending` holding the nodes not yet
+// entered.
+pub struct Iter<'a, K, V> {
+    pending: Vec<&'a Node<K, V>>,
+    current: slice::Iter<'a, (K, V)>,
+    items: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.current.next() {
+                self.items -= 1;
+                return Some((key, value));
+            }
+            match self.pending.pop()? {
+                Node::Branch { children, .. } => {
+                    self.pending.extend(children.iter().map(|child| &**child));
+                }
+                Node::Leaf { entries, .. } => self.current = entries.iter(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K, V, S> IntoIterator for &'a PersistentHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for PersistentHashMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter().fold(
+            PersistentHashMap::with_hasher(S::default()),
+            |map, (key, value)| map.insert(key, value),
+        )
+    }
+}
+
+impl<K, V, S> Default for PersistentHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> fmt::Debug for PersistentHashMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::hash::{BuildHasherDefault, Hasher};
+
+    #[test]
+    fn updates_leave_old_versions_intact() {
+        let base: PersistentHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        let snapshot = base.clone();
+        let updated = base.insert(5, 50).insert(1000, 1000).remove(&0);
+
+        assert_eq!((base.len(), updated.len()), (1000, 1000));
+        assert_eq!(base.get(&5), Some(&5));
+        assert_eq!(updated.get(&5), Some(&50));
+        assert!(base.contains_key(&0) && !updated.contains_key(&0));
+        assert_eq!(updated.remove(&0).len(), 1000);
+        assert_eq!(snapshot.iter().count(), 1000);
+
+        let emptied = (0..1000).fold(updated, |map, i| map.remove(&(i + 1)));
+        assert!(emptied.is_empty());
+        assert!(emptied.root.is_none());
+    }
+
+    #[test]
+    fn colliding_hashes_share_a_leaf() {
+        // Sends every key to one of three hashes.
+        #[derive(Default)]
+        struct ThreeHashes(u64);
+
+        impl Hasher for ThreeHashes {
+            fn finish(&self) -> u64 {
+                self.0 % 3
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for &byte in bytes {
+                    self.0 = self.0.wrapping_mul(31).wrapping_add(byte as u64);
+                }
+            }
+        }
+
+        let mut map = PersistentHashMap::with_hasher(BuildHasherDefault::<ThreeHashes>::default());
+        for i in 0..30 {
+            map = map.insert(i, i * 2);
+        }
+        assert_eq!(map.len(), 30);
+        assert!((0..30).all(|i| map.get(&i) == Some(&(i * 2))));
+
+        let fewer = (0..30)
+            .step_by(2)
+            .fold(map.clone(), |map, i| map.remove(&i));
+        assert_eq!(fewer.len(), 15);
+        assert!((0..30).all(|i| fewer.contains_key(&i) == (i % 2 == 1)));
+        assert_eq!(map.iter().len(), 30);
+    }
+}
@@ -0,0 +1,225 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+
+use crate::HashMap;
+
+struct Slot<K, P, V> {
+    key: K,
+    priority: P,
+    value: V,
+    heap_idx: usize,
+}
+
+// Entries live in a dense slab. The heap orders slab indices by priority and
+// the map resolves a key to its slab index, so every entry knows where it
+// sits in the heap and keyed updates only re-sift a single position.
+pub struct PriorityMap<K, P, V> {
+    slots: Vec<Slot<K, P, V>>,
+    heap: Vec<usize>,
+    index: HashMap<K, usize>,
+}
+
+impl<K, P, V> PriorityMap<K, P, V>
+where
+    K: Hash + Eq + Clone,
+    P: Ord,
+{
+    pub fn new() -> Self {
+        PriorityMap {
+            slots: Vec::new(),
+            heap: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn priority_at(&self, heap_idx: usize) -> &P {
+        &self.slots[self.heap[heap_idx]].priority
+    }
+
+    fn swap_heap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.slots[self.heap[a]].heap_idx = a;
+        self.slots[self.heap[b]].heap_idx = b;
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.priority_at(i) >= self.priority_at(parent) {
+                break;
+            }
+            self.swap_heap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut smallest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len() && self.priority_at(child) < self.priority_at(smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == i {
+                break;
+            }
+            self.swap_heap(i, smallest);
+            i = smallest;
+        }
+    }
+
+    fn resift(&mut self, heap_idx: usize) {
+        self.sift_down(heap_idx);
+        self.sift_up(heap_idx);
+    }
+
+    fn take_slot(&mut self, slot_idx: usize) -> (K, P, V) {
+        let last = self.heap.len() - 1;
+        let heap_idx = self.slots[slot_idx].heap_idx;
+        self.swap_heap(heap_idx, last);
+        self.heap.pop();
+        if heap_idx < self.heap.len() {
+            self.resift(heap_idx);
+        }
+
+        let slot = self.slots.swap_remove(slot_idx);
+        self.index.remove(&slot.key);
+        if let Some(moved) = self.slots.get(slot_idx) {
+            self.heap[moved.heap_idx] = slot_idx;
+            self.index.insert(moved.key.clone(), slot_idx);
+        }
+        (slot.key, slot.priority, slot.value)
+    }
+
+    pub fn push(&mut self, key: K, priority: P, value: V) -> Option<(P, V)> {
+        if let Some(&slot_idx) = self.index.get(&key) {
+            let slot = &mut self.slots[slot_idx];
+            let old_priority = mem::replace(&mut slot.priority, priority);
+            let old_value = mem::replace(&mut slot.value, value);
+            let heap_idx = slot.heap_idx;
+            self.resift(heap_idx);
+            return Some((old_priority, old_value));
+        }
+
+        let slot_idx = self.slots.len();
+        let heap_idx = self.heap.len();
+        self.index.insert(key.clone(), slot_idx);
+        self.slots.push(Slot {
+            key,
+            priority,
+            value,
+            heap_idx,
+        });
+        self.heap.push(slot_idx);
+        self.sift_up(heap_idx);
+        None
+    }
+
+    pub fn peek_min(&self) -> Option<(&K, &P, &V)> {
+        let slot = &self.slots[*self.heap.first()?];
+        Some((&slot.key, &slot.priority, &slot.value))
+    }
+
+    pub fn pop_min(&mut self) -> Option<(K, P, V)> {
+        let slot_idx = *self.heap.first()?;
+        Some(self.take_slot(slot_idx))
+    }
+
+    pub fn change_priority<Q>(&mut self, key: &Q, priority: P) -> Option<P>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let slot = &mut self.slots[*self.index.get(key)?];
+        let old_priority = mem::replace(&mut slot.priority, priority);
+        let heap_idx = slot.heap_idx;
+        self.resift(heap_idx);
+        Some(old_priority)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(P, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let slot_idx = *self.index.get(key)?;
+        let (_, priority, value) = self.take_slot(slot_idx);
+        Some((priority, value))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        Some(&self.slots[*self.index.get(key)?].value)
+    }
+
+    pub fn priority<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        Some(&self.slots[*self.index.get(key)?].priority)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.index.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl<K, P, V> Default for PriorityMap<K, P, V>
+where
+    K: Hash + Eq + Clone,
+    P: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn pops_in_priority_order_after_updates() {
+        let mut queue = PriorityMap::new();
+        for (i, task) in ["e", "b", "d", "a", "c", "f"].into_iter().enumerate() {
+            assert_eq!(queue.push(task, i, task.to_uppercase()), None);
+        }
+        assert_eq!(queue.peek_min().map(|(k, _, _)| *k), Some("e"));
+
+        assert_eq!(queue.change_priority("f", 0), Some(5));
+        assert_eq!(
+            queue.push("e", 10, "E!".to_string()),
+            Some((0, "E".to_string()))
+        );
+        assert_eq!(queue.remove("d"), Some((2, "D".to_string())));
+        assert_eq!(queue.get("e"), Some(&"E!".to_string()));
+        assert_eq!(queue.priority("b"), Some(&1));
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop_min())
+            .map(|(key, _, _)| key)
+            .collect();
+        assert_eq!(order, ["f", "b", "a", "c", "e"]);
+        assert!(queue.is_empty());
+        assert!(!queue.contains_key("a"));
+    }
+}
@@ -0,0 +1,300 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::mem;
+
+use crate::{bucket_for, hash_one, DefaultState, HashValue};
+
+const MIN_SLOTS: usize = 8;
+
+// Which slot a probe visits at each step. Tables always have a power-of-two
+// number of slots, and every strategy must visit all of them within
+// `n_slots` steps, so a probe for a missing key always reaches an empty slot.
+pub trait ProbeStrategy {
+    fn probe(home: usize, step: usize, hash: HashValue, n_slots: usize) -> usize;
+}
+
+// Tries the slots right after home, one by one.
+pub struct Linear;
+
+// Jumps by 1, 2, 3, ... slots, which breaks up the clusters linear probing
+// builds while still covering a power-of-two table.
+pub struct Quadratic;
+
+// Jumps by a stride taken from the hash, so keys that share a home still
+// follow different sequences. The stride is odd, which makes it coprime with
+// the table size.
+pub struct DoubleHashing;
+
+impl ProbeStrategy for Linear {
+    fn probe(home: usize, step: usize, _hash: HashValue, n_slots: usize) -> usize {
+        (home + step) & (n_slots - 1)
+    }
+}
+
+impl ProbeStrategy for Quadratic {
+    fn probe(home: usize, step: usize, _hash: HashValue, n_slots: usize) -> usize {
+        home.wrapping_add(step * (step + 1) / 2) & (n_slots - 1)
+    }
+}
+
+impl ProbeStrategy for DoubleHashing {
+    fn probe(home: usize, step: usize, hash: HashValue, n_slots: usize) -> usize {
+        // Home comes from the high bits of the mixed hash, so the stride is
+        // taken from the low bits of the raw one.
+        let stride = hash as usize | 1;
+        home.wrapping_add(step.wrapping_mul(stride)) & (n_slots - 1)
+    }
+}
+
+enum Slot<K, V> {
+    Empty,
+    // Left behind by a removal so that probes for keys placed further along
+    // the sequence do not stop early.
+    Deleted,
+    Full { hash: HashValue, key: K, value: V },
+}
+
+// Open addressing with the probe sequence picked by a type parameter, so the
+// strategies can be swapped and benchmarked on the same table code. Unlike
+// `HashMap`'s Robin Hood table, which shifts entries back on removal and
+// relies on linear probing to do so, removals leave tombstones. Those count
+// towards the load and are cleared by the next rehash.
+pub struct ProbingHashMap<K, V, P = Linear> {
+    slots: Vec<Slot<K, V>>,
+    hash_builder: DefaultState,
+    items: usize,
+    tombstones: usize,
+    _strategy: PhantomData<P>,
+}
+
+impl<K, V, P> ProbingHashMap<K, V, P>
+where
+    K: Hash + Eq,
+    P: ProbeStrategy,
+{
+    pub fn new() -> Self {
+        ProbingHashMap {
+            slots: Vec::new(),
+            hash_builder: DefaultState::default(),
+            items: 0,
+            tombstones: 0,
+            _strategy: PhantomData,
+        }
+    }
+
+    // Slots along the probe sequence for `hash`, covering the whole table.
+    fn probe_seq(&self, hash: HashValue) -> impl Iterator<Item = usize> {
+        let n_slots = self.slots.len();
+        let home = bucket_for(hash, n_slots);
+        (0..n_slots).map(move |step| P::probe(home, step, hash, n_slots))
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let hash = hash_one(&self.hash_builder, key);
+        for idx in self.probe_seq(hash) {
+            match &self.slots[idx] {
+                Slot::Empty => return None,
+                Slot::Full {
+                    hash: ehash,
+                    key: ekey,
+                    ..
+                } if *ehash == hash && ekey.borrow() == key => return Some(idx),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // Places a key known to be absent in the first free slot of its probe
+    // sequence, reusing a tombstone if it comes first.
+    fn place(&mut self, hash: HashValue, key: K, value: V) {
+        let idx = self
+            .probe_seq(hash)
+            .find(|&idx| !matches!(self.slots[idx], Slot::Full { .. }))
+            .expect("the table always has a free slot");
+        if let Slot::Deleted = self.slots[idx] {
+            self.tombstones -= 1;
+        }
+        self.slots[idx] = Slot::Full { hash, key, value };
+    }
+
+    // Doubles the table if live entries alone would fill half of it, and
+    // otherwise rebuilds it at the same size to clear the tombstones.
+    fn rehash(&mut self) {
+        let n_slots = match self.slots.len() {
+            0 => MIN_SLOTS,
+            n if 2 * (self.items + 1) > n => 2 * n,
+            n => n,
+        };
+        let old_slots = mem::replace(&mut self.slots, (0..n_slots).map(|_| Slot::Empty).collect());
+        self.tombstones = 0;
+        for slot in old_slots {
+            if let Slot::Full { hash, key, value } = slot {
+                self.place(hash, key, value);
+            }
+        }
+    }
+
+    // Panics unless every entry caches its own hash and is found by a probe
+    // for its key, the counts match, and at least one slot is empty.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let (mut items, mut tombstones) = (0, 0);
+        for (idx, slot) in self.slots.iter().enumerate() {
+            match slot {
+                Slot::Empty => {}
+                Slot::Deleted => tombstones += 1,
+                Slot::Full { hash, key, .. } => {
+                    items += 1;
+                    assert_eq!(*hash, hash_one(&self.hash_builder, key), "stale hash");
+                    assert_eq!(self.find(key), Some(idx), "entry off its probe sequence");
+                }
+            }
+        }
+        assert_eq!(items, self.items, "item count is off");
+        assert_eq!(tombstones, self.tombstones, "tombstone count is off");
+        assert!(
+            self.slots.is_empty() || items + tombstones < self.slots.len(),
+            "no empty slot left"
+        );
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(idx) = self.find(&key) {
+            let Slot::Full { value: evalue, .. } = &mut self.slots[idx] else {
+                unreachable!("found slot is full");
+            };
+            return Some(mem::replace(evalue, value));
+        }
+
+        // Tombstones lengthen probes as much as live entries do, so both
+        // count towards the three-quarters load limit.
+        if 4 * (self.items + self.tombstones + 1) > 3 * self.slots.len() {
+            self.rehash();
+        }
+
+        let hash = hash_one(&self.hash_builder, &key);
+        self.place(hash, key, value);
+        self.items += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        match &self.slots[self.find(key)?] {
+            Slot::Full { value, .. } => Some(value),
+            _ => unreachable!("found slot is full"),
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let idx = self.find(key)?;
+        self.items -= 1;
+        self.tombstones += 1;
+        match mem::replace(&mut self.slots[idx], Slot::Deleted) {
+            Slot::Full { value, .. } => Some(value),
+            _ => unreachable!("found slot is full"),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Full { key, value, .. } => Some((key, value)),
+            _ => None,
+        })
+    }
+}
+
+impl<K, V, P> Default for ProbingHashMap<K, V, P>
+where
+    K: Hash + Eq,
+    P: ProbeStrategy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn covers_table<P: ProbeStrategy>() {
+        for n_slots in [8, 64, 1024] {
+            for hash in [0, 1, 6, 0x9e37_79b9] {
+                let mut seen = vec![false; n_slots];
+                for step in 0..n_slots {
+                    seen[P::probe(3, step, hash, n_slots)] = true;
+                }
+                assert!(seen.iter().all(|&s| s), "a probe skipped slots");
+            }
+        }
+    }
+
+    #[test]
+    fn every_strategy_visits_every_slot() {
+        covers_table::<Linear>();
+        covers_table::<Quadratic>();
+        covers_table::<DoubleHashing>();
+    }
+
+    #[test]
+    fn tombstones_are_reused_and_cleared() {
+        let mut map: ProbingHashMap<_, _, Quadratic> = ProbingHashMap::new();
+        for i in 0..1000 {
+            assert_eq!(map.insert(i.to_string(), i), None);
+        }
+        for round in 0..10 {
+            for i in (0..1000).step_by(2) {
+                assert_eq!(map.remove(i.to_string().as_str()), Some(i));
+            }
+            map.check_invariants();
+            for i in (0..1000).step_by(2) {
+                assert_eq!(map.insert(i.to_string(), i), None, "round {round}");
+            }
+        }
+        map.check_invariants();
+
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.slots.len(), 2048);
+        let mut keys: Vec<usize> = map.iter().map(|(_, &value)| value).collect();
+        keys.sort();
+        assert!(keys.into_iter().eq(0..1000));
+    }
+}
@@ -0,0 +1,118 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use crate::fnv::FnvBuildHasher;
+use crate::{hash_one, HashValue};
+
+// Hashes with FNV-1a by default, which has no seed and hashes integers the
+// same on every platform, so rings in different processes place a key on the
+// same node as long as keys and nodes hash through integers and strings.
+// Builds with `hash32` fold hashes to 32 bits and place keys differently.
+pub struct HashRing<N, S = FnvBuildHasher> {
+    // Virtual node positions, kept sorted by hash.
+    ring: Vec<(HashValue, N)>,
+    replicas: usize,
+    hash_builder: S,
+}
+
+impl<N> HashRing<N>
+where
+    N: Hash + Eq + Clone,
+{
+    pub fn new(replicas: usize) -> Self {
+        Self::with_hasher(replicas, FnvBuildHasher::default())
+    }
+}
+
+impl<N, S> HashRing<N, S>
+where
+    N: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    pub fn with_hasher(replicas: usize, hash_builder: S) -> Self {
+        assert!(
+            replicas > 0,
+            "a hash ring needs at least one replica per node"
+        );
+        HashRing {
+            ring: Vec::new(),
+            replicas,
+            hash_builder,
+        }
+    }
+
+    pub fn add_node(&mut self, node: N) -> bool {
+        if self.contains_node(&node) {
+            return false;
+        }
+
+        for replica in 0..self.replicas {
+            let hash = hash_one(&self.hash_builder, &(&node, replica as u64));
+            let i = self.ring.partition_point(|(point, _)| *point < hash);
+            self.ring.insert(i, (hash, node.clone()));
+        }
+        true
+    }
+
+    pub fn remove_node(&mut self, node: &N) -> bool {
+        let before = self.ring.len();
+        self.ring.retain(|(_, n)| n != node);
+        self.ring.len() != before
+    }
+
+    pub fn contains_node(&self, node: &N) -> bool {
+        self.ring.iter().any(|(_, n)| n == node)
+    }
+
+    pub fn node_for<Q>(&self, key: &Q) -> Option<&N>
+    where
+        Q: ?Sized + Hash,
+    {
+        if self.ring.is_empty() {
+            return None;
+        }
+
+        let hash = hash_one(&self.hash_builder, key);
+        let i = self.ring.partition_point(|(point, _)| *point < hash);
+        let (_, node) = &self.ring[i % self.ring.len()];
+        Some(node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len() / self.replicas
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn membership_changes_only_move_affected_keys() {
+        let mut ring = HashRing::new(64);
+        for node in ["a", "b", "c"] {
+            assert!(ring.add_node(node));
+        }
+        assert!(!ring.add_node("a"));
+        assert_eq!(ring.len(), 3);
+
+        let before: Vec<_> = (0..1000usize)
+            .map(|key| *ring.node_for(&key).unwrap())
+            .collect();
+
+        ring.add_node("d");
+        for (key, old_node) in before.iter().enumerate() {
+            let new_node = *ring.node_for(&key).unwrap();
+            assert!(new_node == *old_node || new_node == "d");
+        }
+
+        ring.remove_node(&"d");
+        for (key, old_node) in before.iter().enumerate() {
+            assert_eq!(ring.node_for(&key), Some(old_node));
+        }
+    }
+}
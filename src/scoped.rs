@@ -0,0 +1,127 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+
+use crate::HashMap;
+
+// Each key maps to a stack of (scope depth, value) pairs, innermost last, and
+// each scope remembers which keys it bound so popping it only touches those.
+pub struct ScopedMap<K, V> {
+    bindings: HashMap<K, Vec<(usize, V)>>,
+    scopes: Vec<Vec<K>>,
+}
+
+impl<K, V> ScopedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new() -> Self {
+        ScopedMap {
+            bindings: HashMap::new(),
+            scopes: vec![Vec::new()],
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.scopes.len() - 1
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        assert!(self.depth() > 0, "cannot pop the outermost scope");
+        let scope = self.scopes.pop().unwrap_or_default();
+        for key in scope {
+            let Some(stack) = self.bindings.get_mut(&key) else {
+                continue;
+            };
+            stack.pop();
+            if stack.is_empty() {
+                self.bindings.remove(&key);
+            }
+        }
+    }
+
+    // Binds `key` in the innermost scope. Returns the previous value only when
+    // the key was already bound in that same scope; outer bindings are
+    // shadowed, not replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let depth = self.depth();
+        if let Some((binding_depth, binding)) = self
+            .bindings
+            .get_mut(&key)
+            .and_then(|stack| stack.last_mut())
+        {
+            if *binding_depth == depth {
+                return Some(mem::replace(binding, value));
+            }
+        }
+        self.scopes[depth].push(key.clone());
+        self.bindings.entry(key).or_default().push((depth, value));
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.bindings.get(key)?.last().map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.bindings.contains_key(key)
+    }
+
+    // Number of visible bindings; shadowed ones are not counted.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+impl<K, V> Default for ScopedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inner_scopes_shadow_outer_ones() {
+        let mut env = ScopedMap::new();
+        env.insert("x", 1);
+        env.insert("y", 2);
+
+        env.push_scope();
+        assert_eq!(env.insert("x", 10), None);
+        assert_eq!(env.insert("x", 11), Some(10));
+        env.insert("z", 3);
+        assert_eq!(env.get("x"), Some(&11));
+        assert_eq!(env.get("y"), Some(&2));
+        assert_eq!(env.len(), 3);
+
+        env.pop_scope();
+        assert_eq!(env.depth(), 0);
+        assert_eq!(env.get("x"), Some(&1));
+        assert_eq!(env.get("z"), None);
+        assert_eq!(env.len(), 2);
+    }
+}
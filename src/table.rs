@@ -0,0 +1,122 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{DefaultState, HashMap};
+
+pub struct DisplayTable<'a, K, V, S = DefaultState> {
+    map: &'a HashMap<K, V, S>,
+    headers: Option<(&'a str, &'a str)>,
+    sorted: bool,
+    max_cell_width: Option<usize>,
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn display_table(&self) -> DisplayTable<'_, K, V, S> {
+        DisplayTable {
+            map: self,
+            headers: None,
+            sorted: false,
+            max_cell_width: None,
+        }
+    }
+}
+
+impl<'a, K, V, S> DisplayTable<'a, K, V, S> {
+    pub fn headers(mut self, key: &'a str, value: &'a str) -> Self {
+        self.headers = Some((key, value));
+        self
+    }
+
+    // Sorts rows by the rendered key.
+    pub fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+
+    pub fn truncate(mut self, max_cell_width: usize) -> Self {
+        self.max_cell_width = Some(max_cell_width);
+        self
+    }
+
+    fn cell(&self, text: String) -> String {
+        match self.max_cell_width {
+            Some(max) if text.chars().count() > max => {
+                let mut cell: String = text.chars().take(max.saturating_sub(1)).collect();
+                cell.push('…');
+                cell
+            }
+            _ => text,
+        }
+    }
+}
+
+impl<K, V, S> fmt::Display for DisplayTable<'_, K, V, S>
+where
+    K: fmt::Display,
+    V: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows: Vec<(String, String)> = self
+            .map
+            .iter()
+            .map(|(key, value)| (self.cell(key.to_string()), self.cell(value.to_string())))
+            .collect();
+        if self.sorted {
+            rows.sort();
+        }
+
+        let header = self
+            .headers
+            .map(|(key, value)| (self.cell(key.to_string()), self.cell(value.to_string())));
+        let key_width = header
+            .iter()
+            .chain(&rows)
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        if let Some((key, value)) = &header {
+            writeln!(f, "{key:<key_width$} | {value}")?;
+            let value_width = rows
+                .iter()
+                .map(|(_, value)| value.chars().count())
+                .chain([value.chars().count()])
+                .max()
+                .unwrap_or(0);
+            writeln!(f, "{}-+-{}", "-".repeat(key_width), "-".repeat(value_width))?;
+        }
+        for (key, value) in &rows {
+            writeln!(f, "{key:<key_width$} | {value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_aligned_columns() {
+        let mut map = HashMap::new();
+        map.insert("Pride and Prejudice", "Very enjoyable.");
+        map.insert("Grimms' Fairy Tales", "Masterpiece.");
+        map.insert("Emma", "A long and thoughtful read.");
+
+        let table = map
+            .display_table()
+            .headers("Book", "Review")
+            .sorted()
+            .truncate(16)
+            .to_string();
+        assert_eq!(
+            table,
+            "Book             | Review\n\
+             -----------------+-----------------\n\
+             Emma             | A long and thou…\n\
+             Grimms' Fairy T… | Masterpiece.\n\
+             Pride and Preju… | Very enjoyable.\n"
+        );
+    }
+}
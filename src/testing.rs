@@ -0,0 +1,52 @@
+use core::cell::Cell;
+use core::hash::{BuildHasherDefault, Hash, Hasher};
+
+// Hashes a `u64` to itself, the weakest hash a map can be given.
+#[derive(Default)]
+pub(crate) struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 << 8) | byte as u64;
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+pub(crate) type IdentityState = BuildHasherDefault<IdentityHasher>;
+
+// A key that counts how many times it is hashed, to check that a map reuses
+// cached hashes instead of hashing keys again.
+pub(crate) struct Counted<'a> {
+    id: u32,
+    hashes: &'a Cell<usize>,
+}
+
+impl<'a> Counted<'a> {
+    pub(crate) fn new(id: u32, hashes: &'a Cell<usize>) -> Self {
+        Counted { id, hashes }
+    }
+}
+
+impl Hash for Counted<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hashes.set(self.hashes.get() + 1);
+        self.id.hash(state);
+    }
+}
+
+impl PartialEq for Counted<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Counted<'_> {}
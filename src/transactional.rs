@@ -0,0 +1,148 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+use crate::HashMap;
+
+pub struct TransactionalHashMap<K, V> {
+    map: HashMap<K, V>,
+    // Each record holds a key and the value it had before the change, so
+    // replaying the log backwards restores the state at `begin()`.
+    undo_log: Vec<(K, Option<V>)>,
+    savepoints: Vec<usize>,
+}
+
+impl<K, V> TransactionalHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        TransactionalHashMap {
+            map: HashMap::new(),
+            undo_log: Vec::new(),
+            savepoints: Vec::new(),
+        }
+    }
+
+    pub fn begin(&mut self) {
+        self.savepoints.push(self.undo_log.len());
+    }
+
+    pub fn commit(&mut self) {
+        if self.savepoints.pop().is_some() && self.savepoints.is_empty() {
+            self.undo_log.clear();
+        }
+    }
+
+    pub fn rollback(&mut self) {
+        let Some(savepoint) = self.savepoints.pop() else {
+            return;
+        };
+
+        for (key, old_value) in self.undo_log.drain(savepoint..).rev() {
+            match old_value {
+                Some(value) => {
+                    self.map.insert(key, value);
+                }
+                None => {
+                    self.map.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        !self.savepoints.is_empty()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        if !self.in_transaction() {
+            return self.map.insert(key, value);
+        }
+
+        let old_value = self.map.insert(key.clone(), value);
+        self.undo_log.push((key, old_value.clone()));
+        old_value
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: Clone,
+    {
+        let (key, old_value) = self.map.remove_entry(key)?;
+        if self.in_transaction() {
+            self.undo_log.push((key, Some(old_value.clone())));
+        }
+        Some(old_value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V> Default for TransactionalHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn rollback_and_commit() {
+        let mut map = TransactionalHashMap::new();
+        map.insert("foo".to_string(), 1);
+
+        map.begin();
+        map.insert("foo".to_string(), 2);
+        map.insert("bar".to_string(), 3);
+        assert_eq!(map.remove("foo"), Some(2));
+        map.rollback();
+        assert_eq!(map.get("foo"), Some(&1));
+        assert_eq!(map.get("bar"), None);
+        assert_eq!(map.len(), 1);
+
+        map.begin();
+        map.insert("bar".to_string(), 3);
+        map.begin();
+        map.insert("baz".to_string(), 4);
+        map.rollback();
+        map.commit();
+        assert!(!map.in_transaction());
+        assert_eq!(map.get("bar"), Some(&3));
+        assert_eq!(map.get("baz"), None);
+    }
+}
@@ -0,0 +1,190 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::mem;
+
+use crate::{hash_one, DefaultState, HashValue};
+
+const INITIAL_N_BUCKETS: usize = 1;
+
+// Chained buckets where each key may live in either of two buckets. New keys
+// go to whichever candidate is shorter, which keeps the longest chain short
+// even when the hash function spreads keys poorly.
+pub struct TwoChoiceHashMap<K, V> {
+    buckets: Vec<Vec<(K, V)>>,
+    hash_builder: DefaultState,
+    items: usize,
+}
+
+impl<K, V> TwoChoiceHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        TwoChoiceHashMap {
+            buckets: Vec::new(),
+            hash_builder: DefaultState::default(),
+            items: 0,
+        }
+    }
+
+    fn candidate_idxs<Q>(hash_builder: &DefaultState, key: &Q, n_buckets: usize) -> [usize; 2]
+    where
+        Q: ?Sized + Hash,
+    {
+        [0u8, 1].map(|seed| {
+            let hash = hash_one(hash_builder, &(seed, key));
+            (hash % (n_buckets as HashValue)) as usize
+        })
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<(usize, usize)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+
+        Self::candidate_idxs(&self.hash_builder, key, self.buckets.len())
+            .into_iter()
+            .find_map(|bucket_idx| {
+                self.buckets[bucket_idx]
+                    .iter()
+                    .position(|(ekey, _)| ekey.borrow() == key)
+                    .map(|i| (bucket_idx, i))
+            })
+    }
+
+    fn push(hash_builder: &DefaultState, buckets: &mut [Vec<(K, V)>], key: K, value: V) {
+        let [first, second] = Self::candidate_idxs(hash_builder, &key, buckets.len());
+        let bucket_idx = if buckets[second].len() < buckets[first].len() {
+            second
+        } else {
+            first
+        };
+        buckets[bucket_idx].push((key, value));
+    }
+
+    fn resize(&mut self) {
+        let target_size = match self.buckets.len() {
+            0 => INITIAL_N_BUCKETS,
+            n => 2 * n,
+        };
+
+        let mut new_buckets = Vec::with_capacity(target_size);
+        new_buckets.extend((0..target_size).map(|_| Vec::new()));
+        for (key, value) in self.buckets.iter_mut().flat_map(|bucket| bucket.drain(..)) {
+            Self::push(&self.hash_builder, &mut new_buckets, key, value);
+        }
+
+        self.buckets = new_buckets;
+    }
+
+    // Panics unless every entry sits in one of its two candidate buckets and
+    // the item count matches.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let mut items = 0;
+        for (bucket_idx, bucket) in self.buckets.iter().enumerate() {
+            for (key, _) in bucket {
+                items += 1;
+                let candidates = Self::candidate_idxs(&self.hash_builder, key, self.buckets.len());
+                assert!(candidates.contains(&bucket_idx), "misplaced entry");
+            }
+        }
+        assert_eq!(items, self.items, "item count is off");
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some((bucket_idx, i)) = self.find(&key) {
+            return Some(mem::replace(&mut self.buckets[bucket_idx][i].1, value));
+        }
+
+        if self.buckets.is_empty() || self.items > 3 * self.buckets.len() / 4 {
+            self.resize();
+        }
+
+        self.items += 1;
+        Self::push(&self.hash_builder, &mut self.buckets, key, value);
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (bucket_idx, i) = self.find(key)?;
+        Some(&self.buckets[bucket_idx][i].1)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let (bucket_idx, i) = self.find(key)?;
+        self.items -= 1;
+        Some(self.buckets[bucket_idx].swap_remove(i).1)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    pub fn max_chain_len(&self) -> usize {
+        self.buckets.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+}
+
+impl<K, V> Default for TwoChoiceHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = TwoChoiceHashMap::new();
+        assert_eq!(map.get("foo"), None);
+        for i in 0..1000 {
+            assert_eq!(map.insert(i.to_string(), i), None);
+        }
+        assert_eq!(map.insert("7".to_string(), 70), Some(7));
+        assert_eq!(map.len(), 1000);
+        assert!(map.max_chain_len() <= 4);
+        assert_eq!(map.get("7"), Some(&70));
+        assert_eq!(map.remove("7"), Some(70));
+        assert!(!map.contains_key("7"));
+        assert_eq!(map.iter().count(), 999);
+    }
+}
@@ -0,0 +1,220 @@
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+
+use crate::set::HashSet;
+use crate::{DefaultState, HashIter, HashMap};
+
+pub struct ReadOnlyView<'a, K, V, S = DefaultState> {
+    map: &'a HashMap<K, V, S>,
+}
+
+impl<K, V, S> Clone for ReadOnlyView<'_, K, V, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V, S> Copy for ReadOnlyView<'_, K, V, S> {}
+
+pub struct ReadOnlyMap<K, V, S = DefaultState> {
+    map: HashMap<K, V, S>,
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn as_read_only(&self) -> ReadOnlyView<'_, K, V, S> {
+        ReadOnlyView { map: self }
+    }
+
+    pub fn into_read_only(self) -> ReadOnlyMap<K, V, S> {
+        ReadOnlyMap { map: self }
+    }
+}
+
+impl<'a, K, V, S> ReadOnlyView<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> HashIter<'a, K, V> {
+        self.map.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for ReadOnlyView<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = HashIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+impl<K, V, S> ReadOnlyMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn as_view(&self) -> ReadOnlyView<'_, K, V, S> {
+        self.map.as_read_only()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> HashIter<'_, K, V> {
+        self.map.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a ReadOnlyMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = HashIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+// `S` hashes the key subset and `H` the underlying map.
+pub struct SubMapView<'a, K, V, S = DefaultState, H = DefaultState> {
+    map: &'a HashMap<K, V, H>,
+    keys: &'a HashSet<K, S>,
+}
+
+impl<K, V, H> HashMap<K, V, H>
+where
+    K: Hash + Eq,
+    H: BuildHasher,
+{
+    pub fn view<'a, S>(&'a self, keys: &'a HashSet<K, S>) -> SubMapView<'a, K, V, S, H>
+    where
+        S: BuildHasher,
+    {
+        SubMapView { map: self, keys }
+    }
+}
+
+impl<'a, K, V, S, H> SubMapView<'a, K, V, S, H>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    H: BuildHasher,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        if !self.keys.contains(key) {
+            return None;
+        }
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(key).is_some()
+    }
+
+    // Walks the key subset rather than the map, so this is O(subset size).
+    pub fn iter(&self) -> impl Iterator<Item = (&'a K, &'a V)> + 'a {
+        let map = self.map;
+        self.keys
+            .iter()
+            .filter_map(move |key| Some((key, map.get(key)?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn views_expose_lookups() {
+        let mut map = HashMap::new();
+        map.insert("foo".to_string(), 42);
+
+        let view = map.as_read_only();
+        assert_eq!(view.get("foo"), Some(&42));
+        assert!(!view.contains_key("bar"));
+        assert_eq!(view.iter().count(), 1);
+
+        let owned = map.into_read_only();
+        assert_eq!(owned.len(), 1);
+        assert_eq!(owned.as_view().get("foo"), Some(&42));
+        assert_eq!((&owned).into_iter().next(), Some((&"foo".to_string(), &42)));
+    }
+
+    #[test]
+    fn sub_map_view_restricts_keys() {
+        let mut map = HashMap::new();
+        map.insert("db.host", "localhost");
+        map.insert("db.port", "5432");
+        map.insert("http.port", "8080");
+
+        let keys: HashSet<_> = ["db.host", "db.port", "db.user"].into_iter().collect();
+        let db = map.view(&keys);
+        assert_eq!(db.get("db.port"), Some(&"5432"));
+        assert_eq!(db.get("http.port"), None);
+        assert!(!db.contains_key("db.user"));
+        assert_eq!(db.len(), 2);
+        assert!(db.iter().all(|(key, _)| key.starts_with("db.")));
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::rc::{Rc, Weak};
+use core::hash::{Hash, Hasher};
+
+use crate::HashMap;
+
+const MIN_PRUNE_THRESHOLD: usize = 8;
+
+// Keys are compared by allocation rather than by value: a dead `Weak` can no
+// longer be upgraded to compare contents, and while any `Weak` is alive the
+// allocation cannot be reused, so the address stays unique.
+struct WeakKey<K: ?Sized>(Weak<K>);
+
+impl<K: ?Sized> WeakKey<K> {
+    fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+impl<K: ?Sized> Clone for WeakKey<K> {
+    fn clone(&self) -> Self {
+        WeakKey(self.0.clone())
+    }
+}
+
+impl<K: ?Sized> Hash for WeakKey<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as *const ()).hash(state);
+    }
+}
+
+impl<K: ?Sized> PartialEq for WeakKey<K> {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<K: ?Sized> Eq for WeakKey<K> {}
+
+pub struct WeakKeyMap<K: ?Sized, V> {
+    map: HashMap<WeakKey<K>, V>,
+    prune_threshold: usize,
+}
+
+impl<K: ?Sized, V> WeakKeyMap<K, V> {
+    pub fn new() -> Self {
+        WeakKeyMap {
+            map: HashMap::new(),
+            prune_threshold: MIN_PRUNE_THRESHOLD,
+        }
+    }
+
+    pub fn insert(&mut self, key: &Rc<K>, value: V) -> Option<V> {
+        if self.map.len() >= self.prune_threshold {
+            self.prune();
+            self.prune_threshold = MIN_PRUNE_THRESHOLD.max(2 * self.map.len());
+        }
+        self.map.insert(WeakKey(Rc::downgrade(key)), value)
+    }
+
+    pub fn get(&self, key: &Rc<K>) -> Option<&V> {
+        self.map.get(&WeakKey(Rc::downgrade(key)))
+    }
+
+    pub fn contains_key(&self, key: &Rc<K>) -> bool {
+        self.map.contains_key(&WeakKey(Rc::downgrade(key)))
+    }
+
+    pub fn remove(&mut self, key: &Rc<K>) -> Option<V> {
+        self.map.remove(&WeakKey(Rc::downgrade(key)))
+    }
+
+    pub fn prune(&mut self) {
+        self.map.retain(|key, _| key.is_alive());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Rc<K>, &V)> {
+        self.map
+            .iter()
+            .filter_map(|(key, value)| Some((key.0.upgrade()?, value)))
+    }
+
+    // Counts only entries whose key is still alive, so this walks the map.
+    pub fn len(&self) -> usize {
+        self.map.iter().filter(|(key, _)| key.is_alive()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: ?Sized, V> Default for WeakKeyMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn dropped_keys_are_pruned() {
+        let mut map = WeakKeyMap::new();
+        let alive = Rc::new("alive".to_string());
+        let twin = Rc::new("alive".to_string());
+        map.insert(&alive, 1);
+        assert_eq!(map.get(&alive), Some(&1));
+        assert_eq!(map.get(&twin), None);
+
+        for i in 0..100 {
+            let dropped = Rc::new(i.to_string());
+            map.insert(&dropped, i);
+        }
+        assert_eq!(map.len(), 1);
+        assert!(map.map.len() < 100);
+
+        map.prune();
+        assert_eq!(map.map.len(), 1);
+        assert_eq!(map.iter().next(), Some((alive.clone(), &1)));
+        assert_eq!(map.remove(&alive), Some(1));
+        assert!(map.is_empty());
+    }
+}